@@ -1,69 +1,168 @@
 use core::fmt;
-use vga_framebuffer::freebsd_cp850::FONT_DATA;
+use super::{MAX_WIDTH, HEIGHT, Rect};
+use super::font::{Font, Cp850, Scale2x, Supersampled};
 
-use super::{WIDTH, HEIGHT};
-
-const COLS: usize = WIDTH / FONT_WIDTH;
-const LINES: usize = HEIGHT / FONT_HEIGHT;
+const MAX_COLS: usize = MAX_WIDTH / FONT_WIDTH;
+const MAX_LINES: usize = HEIGHT / FONT_HEIGHT;
 const FONT_WIDTH: usize = 8;
 const FONT_HEIGHT: usize = 16;
+/// The console font anti-aliased at its own size
+const SMOOTH_FONT: Supersampled<Scale2x<Cp850>> = Supersampled {
+    font: Scale2x { font: Cp850::MONOSPACE },
+    factor: 2,
+};
 
+/// Text console whose lines stay at a fixed position in frame
+/// memory. Scrolling only rotates `top`, which is meant to be
+/// displayed with `Display::scroll_to(console.scroll_offset())`.
 pub struct Console {
-    pub buffer: [[char; COLS]; LINES],
+    pub buffer: [[char; MAX_COLS]; MAX_LINES],
+    pub cols: usize,
+    pub lines: usize,
+    /// Buffer index of the first line on screen
+    pub top: usize,
     pub line: usize,
     pub col: usize,
+    /// Changed column range per buffer line
+    dirty: [Option<(usize, usize)>; MAX_LINES],
+    /// Scrolled since the last `take_dirty()`
+    scrolled: bool,
 }
 
 impl Console {
-    pub fn new() -> Self {
+    /// Fill a screen of `width` x `height` pixels
+    pub fn new(width: usize, height: usize) -> Self {
         Console {
-            buffer: [[' '; COLS]; LINES],
+            buffer: [[' '; MAX_COLS]; MAX_LINES],
+            cols: (width / FONT_WIDTH).min(MAX_COLS),
+            lines: (height / FONT_HEIGHT).min(MAX_LINES),
+            top: 0,
             line: 0,
             col: 0,
+            dirty: [None; MAX_LINES],
+            scrolled: false,
         }
     }
 
-    fn scroll(&mut self) {
-        for line in 0..(LINES - 1) {
-            self.buffer[line] = self.buffer[line + 1];
+    fn mark_dirty(&mut self, line: usize, start: usize, end: usize) {
+        self.dirty[line] = Some(match self.dirty[line] {
+            Some((start0, end0)) => (start0.min(start), end0.max(end)),
+            None => (start, end),
+        });
+    }
+
+    /// Call `f` with the area of every changed line segment since
+    /// the last call. With `hardware_scroll` areas are in frame
+    /// memory coordinates, otherwise scrolling dirties all lines.
+    pub fn take_dirty<F: FnMut(Rect)>(&mut self, hardware_scroll: bool, mut f: F) {
+        if self.scrolled && !hardware_scroll {
+            for line in 0..self.lines {
+                self.dirty[line] = Some((0, self.cols));
+            }
+        }
+        self.scrolled = false;
+
+        for line in 0..self.lines {
+            if let Some((start, end)) = self.dirty[line].take() {
+                let y = if hardware_scroll {
+                    line
+                } else {
+                    (line + self.lines - self.top) % self.lines
+                };
+                f(Rect::new(start * FONT_WIDTH, y * FONT_HEIGHT,
+                            (end - start) * FONT_WIDTH, FONT_HEIGHT));
+            }
         }
-        self.buffer[LINES - 1] = [' '; COLS];
+    }
+
+    fn scroll(&mut self) {
+        // The first line becomes the new last line
+        self.buffer[self.top] = [' '; MAX_COLS];
+        let (top, cols) = (self.top, self.cols);
+        self.mark_dirty(top, 0, cols);
+        self.top = (self.top + 1) % self.lines;
+        self.scrolled = true;
         self.line -= 1;
     }
-    
+
+    /// Frame memory line that should be shown at the top of the
+    /// screen
+    pub fn scroll_offset(&self) -> usize {
+        self.top * FONT_HEIGHT
+    }
+
     pub fn add_char(&mut self, ch: char) {
-        if self.col >= COLS {
+        if self.col >= self.cols {
             self.col = 0;
             self.line += 1;
         }
-        while self.line >= LINES {
+        while self.line >= self.lines {
             self.scroll();
         }
 
-        self.buffer[self.line][self.col] = ch;
+        let line = (self.top + self.line) % self.lines;
+        let col = self.col;
+        if self.buffer[line][col] != ch {
+            self.buffer[line][col] = ch;
+            self.mark_dirty(line, col, col + 1);
+        }
         self.col += 1;
     }
 
     pub fn add_nl(&mut self) {
         // Cause scrolling in the next `add_char()` invokation
-        self.col = COLS;
+        self.col = self.cols;
     }
 
-    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+    /// Character and its pixel at frame memory coordinates
+    fn glyph_at(&self, x: usize, y: usize) -> Option<(char, usize, usize)> {
         let col = x / FONT_WIDTH;
         let line = y / FONT_HEIGHT;
-        if col < COLS && line < LINES {
-            let ch = self.buffer[line][col];
-            let y1 = y % FONT_HEIGHT;
-            let line = FONT_DATA[(ch as usize) * FONT_HEIGHT + y1];
-            let x1 = x % FONT_WIDTH;
-            (line & (0x80 >> x1)) != 0
+        if col < self.cols && line < self.lines {
+            Some((self.buffer[line][col], x % FONT_WIDTH, y % FONT_HEIGHT))
         } else {
-            false
+            None
         }
     }
 
-    // TODO: fn render()
+    /// Frame memory row at screen row `y`, for when hardware
+    /// scrolling is not used
+    fn screen_to_frame(&self, y: usize) -> Option<usize> {
+        let height = self.lines * FONT_HEIGHT;
+        if y < height {
+            Some((y + self.scroll_offset()) % height)
+        } else {
+            None
+        }
+    }
+
+    /// Pixel at frame memory coordinates, independent of scrolling
+    #[allow(unused)]
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        self.glyph_at(x, y)
+            .is_some_and(|(ch, x, y)| Cp850::MONOSPACE.get_pixel(ch, x, y))
+    }
+
+    /// Pixel at screen coordinates, for when hardware scrolling is
+    /// not used
+    #[allow(unused)]
+    pub fn get_screen_pixel(&self, x: usize, y: usize) -> bool {
+        self.screen_to_frame(y)
+            .is_some_and(|y| self.get_pixel(x, y))
+    }
+
+    /// Anti-aliased coverage at frame memory coordinates, see
+    /// `font::blend()`
+    pub fn coverage(&self, x: usize, y: usize) -> u8 {
+        self.glyph_at(x, y)
+            .map_or(0, |(ch, x, y)| SMOOTH_FONT.coverage(ch, x, y))
+    }
+
+    /// Anti-aliased coverage at screen coordinates
+    pub fn screen_coverage(&self, x: usize, y: usize) -> u8 {
+        self.screen_to_frame(y)
+            .map_or(0, |y| self.coverage(x, y))
+    }
 }
 
 impl fmt::Write for Console {
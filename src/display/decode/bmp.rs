@@ -0,0 +1,177 @@
+//! Uncompressed 24- and 32-bit Windows bitmaps
+
+use super::{Decoder, DecodeError, read_u16_le, read_u32_le};
+
+const HEADER_LEN: usize = 54;
+
+pub struct Bmp<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    /// Bytes per row including padding
+    stride: usize,
+    /// Rows are stored bottom-up
+    bottom_up: bool,
+    x: usize,
+    y: usize,
+}
+
+impl<'a> Bmp<'a> {
+    #[allow(unused)]
+    pub fn new(data: &'a [u8]) -> Result<Self, DecodeError> {
+        if data.len() < HEADER_LEN {
+            return Err(DecodeError::Truncated);
+        }
+        if &data[0..2] != b"BM" {
+            return Err(DecodeError::BadMagic);
+        }
+
+        let offset = read_u32_le(data, 10) as usize;
+        let width = read_u32_le(data, 18) as i32;
+        let height = read_u32_le(data, 22) as i32;
+        let bpp = read_u16_le(data, 28);
+        let compression = read_u32_le(data, 30);
+        let bytes_per_pixel = match (bpp, compression) {
+            // BI_RGB
+            (24, 0) => 3,
+            (32, 0) => 4,
+            _ => return Err(DecodeError::Unsupported),
+        };
+        if width < 0 {
+            return Err(DecodeError::Unsupported);
+        }
+        // Rows would never end
+        if width == 0 {
+            return Err(DecodeError::Invalid);
+        }
+        let width = width as usize;
+        let bottom_up = height > 0;
+        let height = height.checked_abs()
+            .ok_or(DecodeError::TooLarge)? as usize;
+        let stride = width.checked_mul(bytes_per_pixel)
+            .and_then(|row| row.checked_add(3))
+            .ok_or(DecodeError::TooLarge)? & !3;
+        let end = stride.checked_mul(height)
+            .and_then(|size| size.checked_add(offset))
+            .ok_or(DecodeError::TooLarge)?;
+        if data.len() < end {
+            return Err(DecodeError::Truncated);
+        }
+
+        Ok(Bmp {
+            data: &data[offset..],
+            width, height,
+            bytes_per_pixel, stride,
+            bottom_up,
+            x: 0,
+            y: 0,
+        })
+    }
+}
+
+impl<'a> Iterator for Bmp<'a> {
+    type Item = (u8, u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.y >= self.height {
+            return None;
+        }
+
+        let row = if self.bottom_up {
+            self.height - 1 - self.y
+        } else {
+            self.y
+        };
+        let i = row * self.stride + self.x * self.bytes_per_pixel;
+        // Stored as BGR(A)
+        let pixel = (self.data[i + 2], self.data[i + 1], self.data[i]);
+
+        self.x += 1;
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some(pixel)
+    }
+}
+
+impl<'a> Decoder for Bmp<'a> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 24-bit BMP with rows in file order, padded to 4 bytes
+    fn bmp(width: i32, height: i32, rows: &[&[(u8, u8, u8)]]) -> Vec<u8> {
+        let mut data = vec![0; HEADER_LEN];
+        data[0] = b'B';
+        data[1] = b'M';
+        data[10] = HEADER_LEN as u8;
+        data[14] = 40;
+        for i in 0..4 {
+            data[18 + i] = (width >> (8 * i)) as u8;
+            data[22 + i] = (height >> (8 * i)) as u8;
+        }
+        data[26] = 1;
+        data[28] = 24;
+        for row in rows {
+            for &(r, g, b) in row.iter() {
+                data.extend_from_slice(&[b, g, r]);
+            }
+            while !(data.len() - HEADER_LEN).is_multiple_of(4) {
+                data.push(0);
+            }
+        }
+        data
+    }
+
+    const TOP: &[(u8, u8, u8)] = &[(1, 2, 3), (4, 5, 6), (7, 8, 9)];
+    const BOTTOM: &[(u8, u8, u8)] = &[(11, 12, 13), (14, 15, 16), (17, 18, 19)];
+
+    #[test]
+    fn bottom_up() {
+        let data = bmp(3, 2, &[BOTTOM, TOP]);
+        let image = Bmp::new(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+        let pixels: Vec<_> = image.collect();
+        assert_eq!(&pixels[..3], TOP);
+        assert_eq!(&pixels[3..], BOTTOM);
+    }
+
+    #[test]
+    fn top_down() {
+        let data = bmp(3, -2, &[TOP, BOTTOM]);
+        let pixels: Vec<_> = Bmp::new(&data).unwrap().collect();
+        assert_eq!(&pixels[..3], TOP);
+        assert_eq!(&pixels[3..], BOTTOM);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let data = bmp(3, 2, &[BOTTOM, TOP]);
+        assert_eq!(Bmp::new(&data[..HEADER_LEN - 1]).err(), Some(DecodeError::Truncated));
+        assert_eq!(Bmp::new(&data[..data.len() - 1]).err(), Some(DecodeError::Truncated));
+
+        let mut data = data;
+        data[0] = b'X';
+        assert_eq!(Bmp::new(&data).err(), Some(DecodeError::BadMagic));
+
+        let data = bmp(0, 2, &[]);
+        assert_eq!(Bmp::new(&data).err(), Some(DecodeError::Invalid));
+
+        let data = bmp(3, i32::min_value(), &[]);
+        assert_eq!(Bmp::new(&data).err(), Some(DecodeError::TooLarge));
+        // Overflows on 32-bit targets, too short elsewhere
+        let data = bmp(i32::max_value(), i32::max_value(), &[]);
+        assert!(Bmp::new(&data).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! Streaming image decoders. Pixels are produced row by row from
+//! the top left, so that images can be drawn through scanlines
+//! without decoding them into RAM first.
+
+use stm32f429_hal::spi::Error;
+
+use super::{Display, Rect, ScanLine};
+
+pub mod bmp;
+pub mod qoi;
+pub mod tga;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Not the expected file format
+    BadMagic,
+    /// Valid file, but a variant that is not implemented
+    Unsupported,
+    /// Data ends before the image does
+    Truncated,
+    /// Dimensions overflow the address space
+    TooLarge,
+    /// Header contradicts itself or the format
+    Invalid,
+}
+
+/// Yields the pixels of an image in row-major order
+pub trait Decoder: Iterator<Item = (u8, u8, u8)> {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+}
+
+#[inline(always)]
+fn read_u16_le(data: &[u8], offset: usize) -> u16 {
+    (data[offset] as u16) | ((data[offset + 1] as u16) << 8)
+}
+
+#[inline(always)]
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    (read_u16_le(data, offset) as u32) | ((read_u16_le(data, offset + 2) as u32) << 16)
+}
+
+impl Display {
+    /// Decode `image` onto the screen at `(x, y)`
+    #[allow(unused)]
+    pub fn draw_image<D: Decoder>(&mut self, x: usize, y: usize, mut image: D) -> Result<(), Error> {
+        let area = Rect::new(x, y, image.width(), image.height());
+        let pixel_format = self.pixel_format();
+        let (clipped, mut w) = match self.write_area(area)? {
+            Some(area_writer) => area_writer,
+            None => return Ok(()),
+        };
+
+        // Only the right and the bottom can be clipped
+        let skip_right = area.width - clipped.width;
+        for _ in 0..clipped.height {
+            let scanline = ScanLine::new(pixel_format, clipped.width, |_| {
+                image.next()
+                    .unwrap_or((0, 0, 0))
+            });
+            w.write(scanline)?;
+            for _ in 0..skip_right {
+                image.next();
+            }
+        }
+        Ok(())
+    }
+}
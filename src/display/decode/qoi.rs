@@ -0,0 +1,204 @@
+//! "Quite OK Image Format", https://qoiformat.org/qoi-specification.pdf
+
+use super::{Decoder, DecodeError};
+
+const HEADER_LEN: usize = 14;
+/// Limit from the specification
+const MAX_PIXELS: usize = 400_000_000;
+
+const OP_RGB: u8 = 0xFE;
+const OP_RGBA: u8 = 0xFF;
+const OP_INDEX: u8 = 0b00;
+const OP_DIFF: u8 = 0b01;
+const OP_LUMA: u8 = 0b10;
+const OP_RUN: u8 = 0b11;
+
+pub struct Qoi<'a> {
+    data: &'a [u8],
+    /// Read position in `data`
+    pos: usize,
+    width: usize,
+    height: usize,
+    /// Pixels left to produce
+    remaining: usize,
+    /// Previous pixel as RGBA
+    px: [u8; 4],
+    /// Repetitions of `px` left
+    run: usize,
+    index: [[u8; 4]; 64],
+}
+
+#[inline(always)]
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    ((data[offset] as u32) << 24) |
+    ((data[offset + 1] as u32) << 16) |
+    ((data[offset + 2] as u32) << 8) |
+    (data[offset + 3] as u32)
+}
+
+#[inline(always)]
+fn hash(px: &[u8; 4]) -> usize {
+    ((px[0] as usize) * 3 + (px[1] as usize) * 5 +
+     (px[2] as usize) * 7 + (px[3] as usize) * 11) % 64
+}
+
+impl<'a> Qoi<'a> {
+    #[allow(unused)]
+    pub fn new(data: &'a [u8]) -> Result<Self, DecodeError> {
+        if data.len() < HEADER_LEN {
+            return Err(DecodeError::Truncated);
+        }
+        if &data[0..4] != b"qoif" {
+            return Err(DecodeError::BadMagic);
+        }
+        let width = read_u32_be(data, 4) as usize;
+        let height = read_u32_be(data, 8) as usize;
+        match data[12] {
+            3 | 4 => {}
+            _ => return Err(DecodeError::Unsupported),
+        }
+        let remaining = match width.checked_mul(height) {
+            Some(pixels) if pixels <= MAX_PIXELS => pixels,
+            _ => return Err(DecodeError::TooLarge),
+        };
+
+        Ok(Qoi {
+            data,
+            pos: HEADER_LEN,
+            width, height,
+            remaining,
+            px: [0, 0, 0, 255],
+            run: 0,
+            index: [[0; 4]; 64],
+        })
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let b = self.data.get(self.pos).cloned();
+        self.pos += 1;
+        b
+    }
+
+    /// Decode the next chunk into `px`
+    fn decode_chunk(&mut self) -> Option<()> {
+        let b1 = self.byte()?;
+        match b1 {
+            OP_RGB => {
+                self.px[0] = self.byte()?;
+                self.px[1] = self.byte()?;
+                self.px[2] = self.byte()?;
+            }
+            OP_RGBA => {
+                self.px[0] = self.byte()?;
+                self.px[1] = self.byte()?;
+                self.px[2] = self.byte()?;
+                self.px[3] = self.byte()?;
+            }
+            _ => match b1 >> 6 {
+                OP_INDEX =>
+                    self.px = self.index[(b1 & 0x3F) as usize],
+                OP_DIFF => {
+                    self.px[0] = self.px[0].wrapping_add((b1 >> 4) & 3).wrapping_sub(2);
+                    self.px[1] = self.px[1].wrapping_add((b1 >> 2) & 3).wrapping_sub(2);
+                    self.px[2] = self.px[2].wrapping_add(b1 & 3).wrapping_sub(2);
+                }
+                OP_LUMA => {
+                    let b2 = self.byte()?;
+                    let dg = (b1 & 0x3F).wrapping_sub(32);
+                    self.px[0] = self.px[0].wrapping_add(dg).wrapping_add(b2 >> 4).wrapping_sub(8);
+                    self.px[1] = self.px[1].wrapping_add(dg);
+                    self.px[2] = self.px[2].wrapping_add(dg).wrapping_add(b2 & 0xF).wrapping_sub(8);
+                }
+                OP_RUN =>
+                    // This pixel plus the repetitions
+                    self.run = (b1 & 0x3F) as usize,
+                _ => unreachable!(),
+            },
+        }
+        self.index[hash(&self.px)] = self.px;
+        Some(())
+    }
+}
+
+impl<'a> Iterator for Qoi<'a> {
+    type Item = (u8, u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        if self.run > 0 {
+            self.run -= 1;
+        } else {
+            self.decode_chunk()?;
+        }
+        self.remaining -= 1;
+        Some((self.px[0], self.px[1], self.px[2]))
+    }
+}
+
+impl<'a> Decoder for Qoi<'a> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qoi(width: u32, height: u32, chunks: &[u8]) -> Vec<u8> {
+        let mut data = b"qoif".to_vec();
+        for &n in [width, height].iter() {
+            data.extend_from_slice(&[(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]);
+        }
+        data.extend_from_slice(&[3, 0]);
+        data.extend_from_slice(chunks);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        data
+    }
+
+    #[test]
+    fn decodes_ops() {
+        let first = [10, 20, 30, 255];
+        let data = qoi(3, 2, &[
+            OP_RGB, 10, 20, 30,
+            // dr = -1, dg = +1, db = 0
+            (OP_DIFF << 6) | (1 << 4) | (3 << 2) | 2,
+            // dg = +5, dr - dg = -2, db - dg = +3
+            (OP_LUMA << 6) | (5 + 32), (6 << 4) | 11,
+            (OP_INDEX << 6) | hash(&first) as u8,
+            // 2 pixels
+            (OP_RUN << 6) | 1,
+        ]);
+        let image = Qoi::new(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.collect::<Vec<_>>(), [
+            (10, 20, 30), (9, 21, 30), (12, 26, 38),
+            (10, 20, 30), (10, 20, 30), (10, 20, 30),
+        ]);
+    }
+
+    #[test]
+    fn truncated_chunks_end_early() {
+        let data = qoi(4, 1, &[OP_RGB, 1, 2, 3]);
+        let pixels = Qoi::new(&data[..HEADER_LEN + 4]).unwrap().count();
+        assert_eq!(pixels, 1);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let data = qoi(1, 1, &[OP_RGB, 1, 2, 3]);
+        assert_eq!(Qoi::new(&data[..HEADER_LEN - 1]).err(), Some(DecodeError::Truncated));
+        assert_eq!(Qoi::new(b"qoix\0\0\0\x01\0\0\0\x01\x03\0").err(), Some(DecodeError::BadMagic));
+        let huge = qoi(0xFFFF_FFFF, 0xFFFF_FFFF, &[]);
+        assert_eq!(Qoi::new(&huge).err(), Some(DecodeError::TooLarge));
+        let huge = qoi(20_001, 20_000, &[]);
+        assert_eq!(Qoi::new(&huge).err(), Some(DecodeError::TooLarge));
+    }
+}
@@ -0,0 +1,291 @@
+//! True-colour and greyscale Truevision TGA, uncompressed or
+//! run-length encoded
+
+use super::{Decoder, DecodeError, read_u16_le};
+
+const HEADER_LEN: usize = 18;
+/// RLE packet positions kept to restart bottom-up rows from
+const ROW_MARKS: usize = 64;
+
+pub struct Tga<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    bytes_per_pixel: usize,
+    /// Rows are stored bottom-up
+    bottom_up: bool,
+    /// Columns are stored right-to-left
+    right_to_left: bool,
+    /// Run-length encoded packets
+    rle: bool,
+    /// Offset of the current RLE packet in `data`
+    packet_pos: usize,
+    /// Index of the first pixel in the current RLE packet
+    packet_index: usize,
+    /// `(packet_pos, packet_index)` of the packets holding every
+    /// `mark_stride`th pixel, recorded while walking forward
+    marks: [(usize, usize); ROW_MARKS],
+    /// Number of `marks` recorded
+    marked: usize,
+    /// Pixels between marks, a whole number of rows
+    mark_stride: usize,
+    x: usize,
+    y: usize,
+}
+
+impl<'a> Tga<'a> {
+    #[allow(unused)]
+    pub fn new(data: &'a [u8]) -> Result<Self, DecodeError> {
+        if data.len() < HEADER_LEN {
+            return Err(DecodeError::Truncated);
+        }
+
+        let id_len = data[0] as usize;
+        let color_map_type = data[1];
+        let image_type = data[2];
+        let width = read_u16_le(data, 12) as usize;
+        let height = read_u16_le(data, 14) as usize;
+        let bpp = data[16];
+        let descriptor = data[17];
+        if color_map_type > 1 {
+            return Err(DecodeError::BadMagic);
+        }
+        let (bytes_per_pixel, rle) = match (color_map_type, image_type, bpp) {
+            // True-colour
+            (0, 2, 24) => (3, false),
+            (0, 2, 32) => (4, false),
+            (0, 10, 24) => (3, true),
+            (0, 10, 32) => (4, true),
+            // Greyscale
+            (0, 3, 8) => (1, false),
+            (0, 11, 8) => (1, true),
+            _ => return Err(DecodeError::Unsupported),
+        };
+        let right_to_left = descriptor & (1 << 4) != 0;
+        if rle && right_to_left {
+            return Err(DecodeError::Unsupported);
+        }
+        let offset = HEADER_LEN + id_len;
+        // Compressed size is only known while decoding
+        let min_size = if rle { 0 } else { bytes_per_pixel };
+        let end = width.checked_mul(min_size)
+            .and_then(|row| row.checked_mul(height))
+            .and_then(|size| size.checked_add(offset))
+            .ok_or(DecodeError::TooLarge)?;
+        if data.len() < end {
+            return Err(DecodeError::Truncated);
+        }
+
+        Ok(Tga {
+            data: &data[offset..],
+            width, height,
+            bytes_per_pixel,
+            bottom_up: descriptor & (1 << 5) == 0,
+            right_to_left, rle,
+            packet_pos: 0,
+            packet_index: 0,
+            marks: [(0, 0); ROW_MARKS],
+            marked: 0,
+            mark_stride: (height.div_ceil(ROW_MARKS) * width).max(1),
+            x: 0,
+            y: 0,
+        })
+    }
+
+    /// Offset of the `n`th stored pixel in an RLE image. Packets are
+    /// walked forward from the current one, or from the last mark
+    /// before `n` when it lies behind, which happens once per
+    /// bottom-up row. That way each row walks at most the rows
+    /// between two marks instead of the whole image above it.
+    fn rle_offset(&mut self, n: usize) -> Option<usize> {
+        if n < self.packet_index {
+            // Walking past `n` recorded its mark
+            let (pos, index) = self.marks[n / self.mark_stride];
+            self.packet_pos = pos;
+            self.packet_index = index;
+        }
+
+        loop {
+            let header = *self.data.get(self.packet_pos)?;
+            let count = (header & 0x7F) as usize + 1;
+            while self.marked < ROW_MARKS
+                && self.marked * self.mark_stride < self.packet_index + count
+            {
+                self.marks[self.marked] = (self.packet_pos, self.packet_index);
+                self.marked += 1;
+            }
+            let raw = header & 0x80 == 0;
+            let first = self.packet_pos + 1;
+            if n < self.packet_index + count {
+                let offset = if raw {
+                    first + (n - self.packet_index) * self.bytes_per_pixel
+                } else {
+                    first
+                };
+                return if offset + self.bytes_per_pixel <= self.data.len() {
+                    Some(offset)
+                } else {
+                    None
+                };
+            }
+
+            self.packet_pos = first + if raw {
+                count * self.bytes_per_pixel
+            } else {
+                self.bytes_per_pixel
+            };
+            self.packet_index += count;
+        }
+    }
+}
+
+impl<'a> Iterator for Tga<'a> {
+    type Item = (u8, u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.y >= self.height {
+            return None;
+        }
+
+        let row = if self.bottom_up {
+            self.height - 1 - self.y
+        } else {
+            self.y
+        };
+        let col = if self.right_to_left {
+            self.width - 1 - self.x
+        } else {
+            self.x
+        };
+        let n = row * self.width + col;
+        let i = if self.rle {
+            self.rle_offset(n)?
+        } else {
+            n * self.bytes_per_pixel
+        };
+        let pixel = if self.bytes_per_pixel == 1 {
+            let v = self.data[i];
+            (v, v, v)
+        } else {
+            // Stored as BGR(A)
+            (self.data[i + 2], self.data[i + 1], self.data[i])
+        };
+
+        self.x += 1;
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some(pixel)
+    }
+}
+
+impl<'a> Decoder for Tga<'a> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOP_LEFT: u8 = 1 << 5;
+
+    fn tga(image_type: u8, bpp: u8, width: u16, height: u16, descriptor: u8, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; HEADER_LEN];
+        data[2] = image_type;
+        data[12] = width as u8;
+        data[13] = (width >> 8) as u8;
+        data[14] = height as u8;
+        data[15] = (height >> 8) as u8;
+        data[16] = bpp;
+        data[17] = descriptor;
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn grey(pixels: &[u8]) -> Vec<(u8, u8, u8)> {
+        pixels.iter().map(|&v| (v, v, v)).collect()
+    }
+
+    #[test]
+    fn uncompressed_bottom_up() {
+        let data = tga(2, 24, 2, 2, 0, &[
+            // Bottom row, BGR
+            3, 2, 1, 6, 5, 4,
+            // Top row
+            9, 8, 7, 12, 11, 10,
+        ]);
+        let image = Tga::new(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.collect::<Vec<_>>(), [(7, 8, 9), (10, 11, 12), (1, 2, 3), (4, 5, 6)]);
+    }
+
+    /// Run of 3, then 3 raw pixels, with packets crossing rows
+    const RLE_GREY: &[u8] = &[0x82, 1, 0x02, 2, 3, 4];
+
+    #[test]
+    fn rle_top_down() {
+        let data = tga(11, 8, 3, 2, TOP_LEFT, RLE_GREY);
+        let pixels: Vec<_> = Tga::new(&data).unwrap().collect();
+        assert_eq!(pixels, grey(&[1, 1, 1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn rle_bottom_up() {
+        let data = tga(11, 8, 3, 2, 0, RLE_GREY);
+        let pixels: Vec<_> = Tga::new(&data).unwrap().collect();
+        assert_eq!(pixels, grey(&[2, 3, 4, 1, 1, 1]));
+    }
+
+    #[test]
+    fn rle_bottom_up_past_marks() {
+        let height = 3 * ROW_MARKS as u16 + 1;
+        // A run of 2 per row, with every third row raw
+        let mut body = Vec::new();
+        for row in 0..height {
+            let v = row as u8;
+            if row % 3 == 0 {
+                body.extend_from_slice(&[0x01, v, v]);
+            } else {
+                body.extend_from_slice(&[0x81, v]);
+            }
+        }
+        let data = tga(11, 8, 2, height, 0, &body);
+        let pixels: Vec<_> = Tga::new(&data).unwrap().collect();
+        let expected: Vec<_> = (0..height).rev()
+            .flat_map(|row| vec![row as u8; 2])
+            .collect();
+        assert_eq!(pixels, grey(&expected));
+    }
+
+    #[test]
+    fn rle_true_colour() {
+        let data = tga(10, 24, 2, 1, TOP_LEFT, &[0x81, 3, 2, 1]);
+        let pixels: Vec<_> = Tga::new(&data).unwrap().collect();
+        assert_eq!(pixels, [(1, 2, 3), (1, 2, 3)]);
+    }
+
+    #[test]
+    fn truncated_rle_ends_early() {
+        let data = tga(11, 8, 3, 2, TOP_LEFT, &RLE_GREY[..4]);
+        let pixels: Vec<_> = Tga::new(&data).unwrap().collect();
+        assert_eq!(pixels, grey(&[1, 1, 1, 2]));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let data = tga(2, 24, 2, 2, 0, &[0; 12]);
+        assert_eq!(Tga::new(&data[..HEADER_LEN - 1]).err(), Some(DecodeError::Truncated));
+        assert_eq!(Tga::new(&data[..data.len() - 1]).err(), Some(DecodeError::Truncated));
+        assert_eq!(Tga::new(&tga(1, 8, 2, 2, 0, &[])).err(), Some(DecodeError::Unsupported));
+        assert_eq!(Tga::new(&tga(11, 8, 2, 2, 1 << 4, &[])).err(), Some(DecodeError::Unsupported));
+        // Overflows on 32-bit targets, too short elsewhere
+        assert!(Tga::new(&tga(2, 32, 0xFFFF, 0xFFFF, 0, &[])).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use super::{Display, Rect, ScanLine};
+use stm32f429_hal::spi::Error;
+
+/// Rectangles tracked before merging is forced
+const MAX_RECTS: usize = 8;
+
+/// Collects invalidated screen areas, merging them where that
+/// doesn't cost additional pixels, to redraw only those
+pub struct DirtyRegion {
+    rects: [Rect; MAX_RECTS],
+    len: usize,
+}
+
+impl DirtyRegion {
+    pub fn new() -> Self {
+        DirtyRegion {
+            rects: [Rect::new(0, 0, 0, 0); MAX_RECTS],
+            len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects[..self.len]
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn remove(&mut self, i: usize) -> Rect {
+        let rect = self.rects[i];
+        self.len -= 1;
+        self.rects[i] = self.rects[self.len];
+        rect
+    }
+
+    /// Mark `rect` for redrawing
+    pub fn invalidate(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+
+        // Absorb rects that merge without waste, until none is left
+        let mut rect = rect;
+        let mut i = 0;
+        while i < self.len {
+            let union = rect.union(&self.rects[i]);
+            if union.area() <= rect.area() + self.rects[i].area() {
+                self.remove(i);
+                rect = union;
+                i = 0;
+            } else {
+                i += 1;
+            }
+        }
+
+        if self.len == MAX_RECTS {
+            // Merge with the rect that adds the least waste
+            let mut best = 0;
+            let mut best_waste = usize::max_value();
+            for (i, other) in self.rects().iter().enumerate() {
+                let waste = rect.union(other).area()
+                    .saturating_sub(rect.area() + other.area());
+                if waste < best_waste {
+                    best = i;
+                    best_waste = waste;
+                }
+            }
+            let other = self.remove(best);
+            return self.invalidate(rect.union(&other));
+        }
+
+        self.rects[self.len] = rect;
+        self.len += 1;
+    }
+
+    /// Redraw all invalidated areas with pixels from `f(x, y)` and
+    /// clear the region
+    pub fn render<F: FnMut(usize, usize) -> (u8, u8, u8)>(&mut self, display: &mut Display, mut f: F) -> Result<(), Error> {
+        let pixel_format = display.pixel_format();
+        for rect in self.rects[..self.len].iter() {
+            let (area, mut w) = match display.write_area(*rect)? {
+                Some(area_writer) => area_writer,
+                None => continue,
+            };
+            for y in area.y..area.bottom() {
+                let scanline = ScanLine::new(pixel_format, area.width, |x| f(area.x + x, y));
+                w.write(scanline)?;
+            }
+        }
+        self.clear();
+        Ok(())
+    }
+}
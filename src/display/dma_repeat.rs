@@ -0,0 +1,88 @@
+//! DMA2 stream 3 (SPI1 TX, channel 3) transfers that repeat a single
+//! 16-bit word with memory increment turned off. The HAL only
+//! supports incrementing transfers from a buffer.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+use stm32f429_hal::stm32f429::{DMA2, SPI1};
+
+/// NDTR is 16 bits wide and must stay a multiple of 2 for half-word
+/// memory reads with byte peripheral writes
+const MAX_CHUNK: usize = 0xFFFE;
+
+/// Send `word` `count` times, blocking until done. The caller must
+/// own the DMA stream.
+pub fn write_repeated(word: [u8; 2], count: usize) {
+    // Half-words are unpacked least significant byte first
+    let source: u16 = (word[0] as u16) | ((word[1] as u16) << 8);
+    let dma2 = unsafe { &*DMA2::ptr() };
+    let spi1 = unsafe { &*SPI1::ptr() };
+
+    // The HAL sets up only CR for its transfers and relies on the
+    // direct mode this stream has otherwise, so FCR is put back
+    // once done
+    let fcr = dma2.s3fcr.read().bits();
+
+    let mut bytes = 2 * count;
+    while bytes > 0 {
+        let chunk = bytes.min(MAX_CHUNK);
+
+        dma2.s3cr.modify(|_, w| w.en().clear_bit());
+        while dma2.s3cr.read().en().bit_is_set() {}
+        dma2.lifcr.write(|w| w
+            .ctcif3().set_bit()
+            .chtif3().set_bit()
+            .cteif3().set_bit()
+            .cdmeif3().set_bit()
+            .cfeif3().set_bit()
+        );
+        unsafe {
+            dma2.s3par.write(|w| w.bits(&spi1.dr as *const _ as u32));
+            dma2.s3m0ar.write(|w| w.bits(&source as *const u16 as u32));
+            dma2.s3ndtr.write(|w| w.bits(chunk as u32));
+            // FIFO mode is required for unpacking half-words
+            dma2.s3fcr.write(|w| w
+                .dmdis().set_bit()
+                .fth().bits(0b01)
+            );
+            dma2.s3cr.write(|w| w
+                .chsel().bits(3)
+                // Memory to peripheral
+                .dir().bits(0b01)
+                .minc().clear_bit()
+                .pinc().clear_bit()
+                // Half-word
+                .msize().bits(0b01)
+                // Byte
+                .psize().bits(0b00)
+                .circ().clear_bit()
+            );
+        }
+        compiler_fence(Ordering::SeqCst);
+
+        spi1.cr2.modify(|_, w| w.txdmaen().set_bit());
+        dma2.s3cr.modify(|_, w| w.en().set_bit());
+        loop {
+            let lisr = dma2.lisr.read();
+            // Errors are ignored like in `DisplaySpi::flush()`
+            if lisr.tcif3().bit_is_set() || lisr.teif3().bit_is_set() {
+                break;
+            }
+        }
+        compiler_fence(Ordering::SeqCst);
+
+        bytes -= chunk;
+    }
+
+    // FCR can only be written while the stream is disabled
+    dma2.s3cr.modify(|_, w| w.en().clear_bit());
+    while dma2.s3cr.read().en().bit_is_set() {}
+    dma2.s3fcr.write(|w| unsafe { w.bits(fcr) });
+
+    // Wait for the last byte to be shifted out
+    while spi1.sr.read().txe().bit_is_clear() {}
+    while spi1.sr.read().bsy().bit_is_set() {}
+    spi1.cr2.modify(|_, w| w.txdmaen().clear_bit());
+    // Clear overrun from the bytes received meanwhile
+    let _ = spi1.dr.read();
+    let _ = spi1.sr.read();
+}
@@ -0,0 +1,587 @@
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU16, Ordering};
+use vga_framebuffer::Char;
+use vga_framebuffer::freebsd_cp850::FONT_DATA;
+
+use super::Rect;
+
+/// Full coverage of a pixel by a glyph
+pub const MAX_COVERAGE: u8 = 15;
+
+/// Bitmap font whose glyphs may differ in width
+pub trait Font {
+    /// Line height in pixels
+    fn height(&self) -> usize;
+    /// Horizontal distance to the next glyph, including spacing
+    fn advance(&self, ch: char) -> usize;
+    /// Whether `(x, y)` within the advance box of `ch` is set
+    fn get_pixel(&self, ch: char, x: usize, y: usize) -> bool;
+    /// How much of `(x, y)` is covered, `0..=MAX_COVERAGE`
+    fn coverage(&self, ch: char, x: usize, y: usize) -> u8 {
+        if self.get_pixel(ch, x, y) {
+            MAX_COVERAGE
+        } else {
+            0
+        }
+    }
+}
+
+/// Mix `foreground` over `background` by `coverage`
+pub fn blend(background: (u8, u8, u8), foreground: (u8, u8, u8), coverage: u8) -> (u8, u8, u8) {
+    let a = coverage.min(MAX_COVERAGE) as u16;
+    let mix = |bg: u8, fg: u8| {
+        ((bg as u16 * (MAX_COVERAGE as u16 - a) + fg as u16 * a) / MAX_COVERAGE as u16) as u8
+    };
+    (mix(background.0, foreground.0),
+     mix(background.1, foreground.1),
+     mix(background.2, foreground.2))
+}
+
+impl<F: Font + ?Sized> Font for &F {
+    fn height(&self) -> usize {
+        (**self).height()
+    }
+
+    fn advance(&self, ch: char) -> usize {
+        (**self).advance(ch)
+    }
+
+    fn get_pixel(&self, ch: char, x: usize, y: usize) -> bool {
+        (**self).get_pixel(ch, x, y)
+    }
+
+    fn coverage(&self, ch: char, x: usize, y: usize) -> u8 {
+        (**self).coverage(ch, x, y)
+    }
+}
+
+const CP850_WIDTH: usize = 8;
+const CP850_HEIGHT: usize = 16;
+/// Blank columns after each proportional glyph
+const CP850_SPACING: usize = 1;
+
+/// `CP850_INK` entry of a glyph that hasn't been scanned yet
+const INK_UNKNOWN: u16 = 0xFFFF;
+/// Result of `Cp850::scan_ink()` per glyph, as `first << 8 |
+/// width`, filled in on first use
+static CP850_INK: [AtomicU16; 256] = [const { AtomicU16::new(INK_UNKNOWN) }; 256];
+
+/// The 8x16 console font. When `proportional` blank columns are
+/// trimmed off both sides of every glyph.
+#[derive(Debug, Clone, Copy)]
+pub struct Cp850 {
+    pub proportional: bool,
+}
+
+impl Cp850 {
+    pub const MONOSPACE: Cp850 = Cp850 { proportional: false };
+    pub const PROPORTIONAL: Cp850 = Cp850 { proportional: true };
+
+    /// Code page 850 byte of `ch`, '?' if it has none
+    fn glyph_index(ch: char) -> usize {
+        Char::map_char(ch).to_byte() as usize
+    }
+
+    fn glyph_row(ch: char, y: usize) -> u8 {
+        FONT_DATA[Self::glyph_index(ch) * CP850_HEIGHT + y]
+    }
+
+    /// First set column and the number of columns up to the last
+    /// set one, or `None` for blank glyphs
+    fn ink_columns(ch: char) -> Option<(usize, usize)> {
+        let entry = &CP850_INK[Self::glyph_index(ch)];
+        let mut ink = entry.load(Ordering::Relaxed);
+        if ink == INK_UNKNOWN {
+            ink = Self::scan_ink(ch);
+            entry.store(ink, Ordering::Relaxed);
+        }
+        let (first, width) = ((ink >> 8) as usize, (ink & 0xFF) as usize);
+        if width == 0 {
+            None
+        } else {
+            Some((first, width))
+        }
+    }
+
+    /// Combine all rows of a glyph into `first << 8 | width`
+    fn scan_ink(ch: char) -> u16 {
+        let mut mask = 0;
+        for y in 0..CP850_HEIGHT {
+            mask |= Self::glyph_row(ch, y);
+        }
+        if mask == 0 {
+            0
+        } else {
+            let first = mask.leading_zeros() as u16;
+            let last = (CP850_WIDTH - 1) as u16 - mask.trailing_zeros() as u16;
+            first << 8 | (last + 1 - first)
+        }
+    }
+}
+
+impl Font for Cp850 {
+    fn height(&self) -> usize {
+        CP850_HEIGHT
+    }
+
+    fn advance(&self, ch: char) -> usize {
+        if !self.proportional {
+            return CP850_WIDTH;
+        }
+        match Self::ink_columns(ch) {
+            Some((_, width)) => width + CP850_SPACING,
+            // Space and friends
+            None => CP850_WIDTH / 2,
+        }
+    }
+
+    fn get_pixel(&self, ch: char, x: usize, y: usize) -> bool {
+        let x = if self.proportional {
+            match Self::ink_columns(ch) {
+                Some((first, _)) => first + x,
+                None => return false,
+            }
+        } else {
+            x
+        };
+        x < CP850_WIDTH && y < CP850_HEIGHT &&
+            (Self::glyph_row(ch, y) & (0x80 >> x)) != 0
+    }
+}
+
+/// Another font magnified by an integer factor
+#[derive(Debug, Clone, Copy)]
+pub struct Scaled<F> {
+    pub font: F,
+    pub scale: usize,
+}
+
+impl<F: Font> Scaled<F> {
+    pub fn new(font: F, scale: usize) -> Self {
+        assert!(scale > 0, "font scale");
+        Scaled { font, scale }
+    }
+}
+
+impl<F: Font> Font for Scaled<F> {
+    fn height(&self) -> usize {
+        self.scale * self.font.height()
+    }
+
+    fn advance(&self, ch: char) -> usize {
+        self.scale * self.font.advance(ch)
+    }
+
+    fn get_pixel(&self, ch: char, x: usize, y: usize) -> bool {
+        self.font.get_pixel(ch, x / self.scale, y / self.scale)
+    }
+
+    fn coverage(&self, ch: char, x: usize, y: usize) -> u8 {
+        self.font.coverage(ch, x / self.scale, y / self.scale)
+    }
+}
+
+/// Another font doubled in size with the Scale2x algorithm, which
+/// rounds off diagonal steps instead of repeating them. Reduce it
+/// again with `Supersampled` to anti-alias a 1-bit font at its
+/// original size.
+#[derive(Debug, Clone, Copy)]
+pub struct Scale2x<F> {
+    pub font: F,
+}
+
+impl<F: Font> Font for Scale2x<F> {
+    fn height(&self) -> usize {
+        2 * self.font.height()
+    }
+
+    fn advance(&self, ch: char) -> usize {
+        2 * self.font.advance(ch)
+    }
+
+    fn get_pixel(&self, ch: char, x: usize, y: usize) -> bool {
+        let (x, y, right, bottom) = (x / 2, y / 2, !x.is_multiple_of(2), !y.is_multiple_of(2));
+        let pixel = |x: usize, y: usize| self.font.get_pixel(ch, x, y);
+        let center = pixel(x, y);
+        let above = y > 0 && pixel(x, y - 1);
+        let below = pixel(x, y + 1);
+        let left = x > 0 && pixel(x - 1, y);
+        let after = pixel(x + 1, y);
+
+        // Neighbours that meet at this corner, and the opposite ones
+        let (vertical, horizontal, other_vertical, other_horizontal) = match (right, bottom) {
+            (false, false) => (above, left, below, after),
+            (true, false) => (above, after, below, left),
+            (false, true) => (below, left, above, after),
+            (true, true) => (below, after, above, left),
+        };
+        if vertical == horizontal && vertical != other_vertical && horizontal != other_horizontal {
+            vertical
+        } else {
+            center
+        }
+    }
+}
+
+/// Another font reduced by an integer factor, averaging each
+/// `factor` x `factor` block into smooth edges. Combined with
+/// `Scaled` this yields fractional sizes, e.g. 3/2.
+#[derive(Debug, Clone, Copy)]
+pub struct Supersampled<F> {
+    pub font: F,
+    pub factor: usize,
+}
+
+impl<F: Font> Supersampled<F> {
+    #[allow(unused)]
+    pub fn new(font: F, factor: usize) -> Self {
+        assert!(factor > 0, "supersampling factor");
+        Supersampled { font, factor }
+    }
+}
+
+impl<F: Font> Font for Supersampled<F> {
+    fn height(&self) -> usize {
+        self.font.height().div_ceil(self.factor)
+    }
+
+    fn advance(&self, ch: char) -> usize {
+        self.font.advance(ch).div_ceil(self.factor)
+    }
+
+    fn get_pixel(&self, ch: char, x: usize, y: usize) -> bool {
+        self.coverage(ch, x, y) > MAX_COVERAGE / 2
+    }
+
+    fn coverage(&self, ch: char, x: usize, y: usize) -> u8 {
+        let mut sum = 0;
+        for y1 in (y * self.factor)..((y + 1) * self.factor) {
+            for x1 in (x * self.factor)..((x + 1) * self.factor) {
+                sum += self.font.coverage(ch, x1, y1) as usize;
+            }
+        }
+        (sum / (self.factor * self.factor)) as u8
+    }
+}
+
+/// Glyph of an `AaFont`
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct AaGlyph {
+    pub ch: char,
+    /// Stored columns
+    pub width: u8,
+    pub advance: u8,
+    /// Start in `AaFont::data`
+    pub offset: u16,
+}
+
+/// Anti-aliased font with 4-bit coverage per pixel, usually stored
+/// in flash. Each glyph row takes `width.div_ceil(2)` bytes, the left
+/// pixel in the high nibble.
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct AaFont {
+    pub height: usize,
+    /// Sorted by `ch`
+    pub glyphs: &'static [AaGlyph],
+    pub data: &'static [u8],
+}
+
+impl AaFont {
+    #[allow(unused)]
+    fn glyph(&self, ch: char) -> Option<&AaGlyph> {
+        self.glyphs.binary_search_by_key(&ch, |glyph| glyph.ch)
+            .ok()
+            .map(|i| &self.glyphs[i])
+    }
+}
+
+impl Font for AaFont {
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn advance(&self, ch: char) -> usize {
+        match self.glyph(ch) {
+            Some(glyph) => glyph.advance as usize,
+            None => self.height / 4,
+        }
+    }
+
+    fn get_pixel(&self, ch: char, x: usize, y: usize) -> bool {
+        self.coverage(ch, x, y) > MAX_COVERAGE / 2
+    }
+
+    fn coverage(&self, ch: char, x: usize, y: usize) -> u8 {
+        let glyph = match self.glyph(ch) {
+            Some(glyph) if x < glyph.width as usize && y < self.height => glyph,
+            _ => return 0,
+        };
+        let stride = (glyph.width as usize).div_ceil(2);
+        let byte = self.data[glyph.offset as usize + y * stride + x / 2];
+        if x.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0xF
+        }
+    }
+}
+
+/// A single line of text at any pixel position, to be queried from
+/// a scanline callback
+#[derive(Debug, Clone)]
+pub struct Text<'a, F> {
+    font: F,
+    text: &'a str,
+    pub x: usize,
+    pub y: usize,
+    /// Byte offset in `text` and x offset of the glyph last found by
+    /// `glyph_at()`, where the search continues along a row
+    cursor: Cell<(usize, usize)>,
+}
+
+impl<'a, F: Font> Text<'a, F> {
+    pub fn new(font: F, text: &'a str, x: usize, y: usize) -> Self {
+        Text {
+            font, text, x, y,
+            cursor: Cell::new((0, 0)),
+        }
+    }
+
+    #[allow(unused)]
+    pub fn font(&self) -> &F {
+        &self.font
+    }
+
+    #[allow(unused)]
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    #[allow(unused)]
+    pub fn set_text(&mut self, text: &'a str) {
+        self.text = text;
+        self.cursor.set((0, 0));
+    }
+
+    pub fn width(&self) -> usize {
+        self.text.chars()
+            .map(|ch| self.font.advance(ch))
+            .sum()
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width(), self.font.height())
+    }
+
+    /// Glyph and its pixel at screen coordinates. Pixels queried
+    /// left to right only step over each glyph once.
+    fn glyph_at(&self, x: usize, y: usize) -> Option<(char, usize, usize)> {
+        if x < self.x || y < self.y || y >= self.y + self.font.height() {
+            return None;
+        }
+
+        let x = x - self.x;
+        let y = y - self.y;
+        let (mut offset, mut glyph_x) = self.cursor.get();
+        if x < glyph_x {
+            offset = 0;
+            glyph_x = 0;
+        }
+        for ch in self.text[offset..].chars() {
+            let advance = self.font.advance(ch);
+            if x < glyph_x + advance {
+                self.cursor.set((offset, glyph_x));
+                return Some((ch, x - glyph_x, y));
+            }
+            offset += ch.len_utf8();
+            glyph_x += advance;
+        }
+        None
+    }
+
+    /// Pixel at screen coordinates
+    #[allow(unused)]
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        self.glyph_at(x, y)
+            .is_some_and(|(ch, x, y)| self.font.get_pixel(ch, x, y))
+    }
+
+    /// Coverage at screen coordinates
+    pub fn coverage(&self, x: usize, y: usize) -> u8 {
+        self.glyph_at(x, y)
+            .map_or(0, |(ch, x, y)| self.font.coverage(ch, x, y))
+    }
+
+    /// Draw over `background`, the colour the scanline callback
+    /// would produce at `(x, y)` without this text
+    #[allow(unused)]
+    pub fn blend(&self, x: usize, y: usize, background: (u8, u8, u8), foreground: (u8, u8, u8)) -> (u8, u8, u8) {
+        match self.coverage(x, y) {
+            0 => background,
+            coverage => blend(background, foreground, coverage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Left half of every 2x2 block set
+    struct Stripes;
+
+    impl Font for Stripes {
+        fn height(&self) -> usize {
+            2
+        }
+
+        fn advance(&self, _ch: char) -> usize {
+            4
+        }
+
+        fn get_pixel(&self, _ch: char, x: usize, _y: usize) -> bool {
+            x % 4 < 2
+        }
+    }
+
+    #[test]
+    fn blend_by_coverage() {
+        let (bg, fg) = ((0, 100, 255), (255, 0, 45));
+        assert_eq!(blend(bg, fg, 0), bg);
+        assert_eq!(blend(bg, fg, MAX_COVERAGE), fg);
+        assert_eq!(blend((0, 0, 0), (255, 30, 150), 7), (119, 14, 70));
+        // Clamped
+        assert_eq!(blend(bg, fg, 0xFF), fg);
+    }
+
+    #[test]
+    fn supersampled_coverage() {
+        let font = Supersampled::new(Stripes, 2);
+        assert_eq!((font.height(), font.advance('a')), (1, 2));
+        assert_eq!(font.coverage('a', 0, 0), MAX_COVERAGE);
+        assert_eq!(font.coverage('a', 1, 0), 0);
+        let font = Supersampled::new(Stripes, 4);
+        assert_eq!(font.coverage('a', 0, 0), MAX_COVERAGE / 2);
+    }
+
+    #[test]
+    fn text_blends_over_background() {
+        let text = Text::new(Stripes, "ab", 10, 20);
+        assert_eq!(text.bounds(), Rect::new(10, 20, 8, 2));
+        assert_eq!(text.blend(14, 21, (1, 2, 3), (9, 9, 9)), (9, 9, 9));
+        assert_eq!(text.blend(16, 21, (1, 2, 3), (9, 9, 9)), (1, 2, 3));
+        assert_eq!(text.blend(14, 22, (1, 2, 3), (9, 9, 9)), (1, 2, 3));
+    }
+
+    #[test]
+    fn text_pixels_in_any_order() {
+        let font = Cp850::PROPORTIONAL;
+        let mut text = Text::new(font, "Wi ü", 3, 0);
+        let expected = |text: &Text<Cp850>, x: usize, y: usize| {
+            let mut x = x - text.x;
+            for ch in text.text().chars() {
+                if x < font.advance(ch) {
+                    return font.get_pixel(ch, x, y);
+                }
+                x -= font.advance(ch);
+            }
+            false
+        };
+        let width = text.width();
+        for y in 0..font.height() {
+            let xs = (3..3 + width).chain((3..3 + width).rev());
+            for x in xs {
+                assert_eq!(text.get_pixel(x, y), expected(&text, x, y), "({}, {})", x, y);
+            }
+        }
+
+        // 'ü' is glyph 0x81 in code page 850
+        let umlaut = &FONT_DATA[0x81 * CP850_HEIGHT..0x82 * CP850_HEIGHT];
+        let (first, ink_width) = Cp850::ink_columns('ü').unwrap();
+        let umlaut_x = 3 + width - font.advance('ü');
+        for (y, &row) in umlaut.iter().enumerate() {
+            for x in 0..ink_width {
+                let set = row & (0x80 >> (first + x)) != 0;
+                assert_eq!(text.get_pixel(umlaut_x + x, y), set, "ü ({}, {})", x, y);
+            }
+        }
+
+        text.set_text("i");
+        assert_eq!(text.width(), font.advance('i'));
+        assert!(!text.get_pixel(3 + width - 1, 8));
+    }
+
+    #[test]
+    fn cp850_code_page() {
+        let glyph = |byte: usize| &FONT_DATA[byte * CP850_HEIGHT..(byte + 1) * CP850_HEIGHT];
+        for &(ch, byte) in &[('A', 0x41), ('ü', 0x81), ('½', 0xAB), ('\u{2603}', b'?' as usize)] {
+            for y in 0..CP850_HEIGHT {
+                assert_eq!(Cp850::glyph_row(ch, y), glyph(byte)[y], "{:?}", ch);
+            }
+        }
+    }
+
+    #[test]
+    fn cp850_ink_table() {
+        assert_eq!(Cp850::ink_columns(' '), None);
+        assert_eq!(Cp850::PROPORTIONAL.advance(' '), CP850_WIDTH / 2);
+        for &ch in &['W', 'i', '\u{2603}'] {
+            let ink = Cp850::scan_ink(ch);
+            let expected = Some(((ink >> 8) as usize, (ink & 0xFF) as usize));
+            // Scanned on first use, then looked up
+            assert_eq!(Cp850::ink_columns(ch), expected);
+            assert_eq!(Cp850::ink_columns(ch), expected);
+        }
+        assert!(Cp850::PROPORTIONAL.advance('i') < Cp850::PROPORTIONAL.advance('W'));
+    }
+
+    /// Diagonal staircase, which Scale2x rounds off
+    struct Diagonal;
+
+    impl Font for Diagonal {
+        fn height(&self) -> usize {
+            2
+        }
+
+        fn advance(&self, _ch: char) -> usize {
+            2
+        }
+
+        fn get_pixel(&self, _ch: char, x: usize, y: usize) -> bool {
+            x < 2 && x == y
+        }
+    }
+
+    #[test]
+    fn smoothed_diagonal() {
+        let font = Supersampled::new(Scale2x { font: Diagonal }, 2);
+        // The steps stay solid, the gaps beside them gain a corner
+        assert_eq!(font.coverage('a', 0, 0), MAX_COVERAGE);
+        assert_eq!(font.coverage('a', 1, 0), MAX_COVERAGE / 4);
+        assert_eq!(font.coverage('a', 0, 1), MAX_COVERAGE / 4);
+        assert_eq!(font.coverage('a', 1, 1), MAX_COVERAGE);
+    }
+
+    static GLYPHS: [AaGlyph; 2] = [
+        AaGlyph { ch: 'a', width: 3, advance: 4, offset: 0 },
+        AaGlyph { ch: 'b', width: 1, advance: 2, offset: 4 },
+    ];
+    static DATA: [u8; 6] = [0xF8, 0x00, 0x0F, 0x70, 0x50, 0xA0];
+
+    #[test]
+    fn aa_font_nibbles() {
+        let font = AaFont { height: 2, glyphs: &GLYPHS, data: &DATA };
+        assert_eq!(font.advance('a'), 4);
+        assert_eq!(font.advance('?'), 0);
+        assert_eq!([font.coverage('a', 0, 0), font.coverage('a', 1, 0), font.coverage('a', 2, 0)],
+                   [0xF, 0x8, 0x0]);
+        assert_eq!([font.coverage('a', 0, 1), font.coverage('a', 1, 1), font.coverage('a', 2, 1)],
+                   [0x0, 0xF, 0x7]);
+        assert_eq!([font.coverage('b', 0, 0), font.coverage('b', 0, 1)], [0x5, 0xA]);
+        // Outside the stored columns
+        assert_eq!(font.coverage('a', 3, 0), 0);
+        assert!(font.get_pixel('a', 1, 0));
+    }
+}
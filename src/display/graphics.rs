@@ -0,0 +1,107 @@
+//! embedded-graphics support, drawing through windowed writes
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, OriginDimensions, Size},
+    pixelcolor::{Rgb888, RgbColor},
+    primitives::Rectangle,
+    Pixel,
+};
+use stm32f429_hal::spi::Error;
+
+use super::{Display, Rect, ScanLine};
+
+#[inline(always)]
+fn rgb(color: Rgb888) -> (u8, u8, u8) {
+    (color.r(), color.g(), color.b())
+}
+
+impl Display {
+    /// Visible part of `area`
+    fn clip_rectangle(&self, area: &Rectangle) -> Option<Rect> {
+        let area = area.intersection(&self.bounding_box());
+        if area.size.width == 0 || area.size.height == 0 {
+            return None;
+        }
+        Some(Rect::new(
+            area.top_left.x as usize, area.top_left.y as usize,
+            area.size.width as usize, area.size.height as usize
+        ))
+    }
+}
+
+impl OriginDimensions for Display {
+    fn size(&self) -> Size {
+        Size::new(self.width() as u32, self.height() as u32)
+    }
+}
+
+impl DrawTarget for Display {
+    type Color = Rgb888;
+    type Error = Error;
+
+    /// Every pixel sets up its own window, prefer the fill methods
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let pixel_format = self.pixel_format();
+        for Pixel(point, color) in pixels {
+            let rect = match self.clip_rectangle(&Rectangle::new(point, Size::new(1, 1))) {
+                Some(rect) => rect,
+                None => continue,
+            };
+            if let Some((_, mut w)) = self.write_area(rect)? {
+                w.write(ScanLine::new(pixel_format, 1, |_| rgb(color)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let clipped = match self.clip_rectangle(area) {
+            Some(clipped) => clipped,
+            None => return Ok(()),
+        };
+        let pixel_format = self.pixel_format();
+        let (clipped, mut w) = match self.write_area(clipped)? {
+            Some(area_writer) => area_writer,
+            None => return Ok(()),
+        };
+
+        // `colors` covers all of `area`, skip what is clipped
+        let mut colors = colors.into_iter();
+        let width = area.size.width as usize;
+        let skip_left = (clipped.x as i32 - area.top_left.x) as usize;
+        let skip_right = width - skip_left - clipped.width;
+        let skip_top = (clipped.y as i32 - area.top_left.y) as usize;
+        for _ in 0..(skip_top * width) {
+            colors.next();
+        }
+        for _ in 0..clipped.height {
+            for _ in 0..skip_left {
+                colors.next();
+            }
+            let scanline = ScanLine::new(pixel_format, clipped.width, |_| {
+                colors.next()
+                    .map(rgb)
+                    .unwrap_or((0, 0, 0))
+            });
+            w.write(scanline)?;
+            for _ in 0..skip_right {
+                colors.next();
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        match self.clip_rectangle(area) {
+            Some(clipped) => self.fill_rect(clipped, rgb(color)),
+            None => Ok(()),
+        }
+    }
+}
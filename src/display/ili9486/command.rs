@@ -5,6 +5,15 @@ pub trait Command {
     fn encode(self) -> Self::Buffer;
 }
 
+/// Read counterpart of `Command`, decoding the parameters the
+/// controller returns after the dummy read cycle
+pub trait ReadCommand: Sized {
+    type Buffer: AsMut<[u8]> + Default;
+
+    fn number() -> u8;
+    fn decode(buf: Self::Buffer) -> Self;
+}
+
 macro_rules! simple_command {
     ($name: ident, $number: tt) => (
         #[allow(unused)]
@@ -38,13 +47,50 @@ impl<'a> Command for MemoryWrite<'a> {
     }
 }
 
+/// Like `MemoryWrite` but continues after the last pixel written
+pub struct MemoryWriteContinue<'a>(pub &'a mut [u8]);
+
+impl<'a> Command for MemoryWriteContinue<'a> {
+    type Buffer = &'a [u8];
+
+    fn number() -> u8 {
+        0x3C
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.0
+    }
+}
+
+/// Followed by a stream of pixel reads, see `Tft::reader()`
+pub struct MemoryRead;
+
+impl MemoryRead {
+    pub fn number() -> u8 {
+        0x2E
+    }
+}
+
+/// Like `MemoryRead` but continues from the last pixel read
+pub struct MemoryReadContinue;
+
+impl MemoryReadContinue {
+    pub fn number() -> u8 {
+        0x3E
+    }
+}
+
 simple_command!(SleepIn, 0x10);
 simple_command!(SleepOut, 0x11);
 simple_command!(PartialModeOn, 0x12);
+simple_command!(NormalDisplayModeOn, 0x13);
+simple_command!(InversionOff, 0x20);
 simple_command!(InversionOn, 0x21);
-simple_command!(InversionOff, 0x28);
 simple_command!(DisplayOn, 0x29);
 simple_command!(DisplayOff, 0x28);
+simple_command!(TearingEffectLineOff, 0x34);
+simple_command!(IdleModeOff, 0x38);
+simple_command!(IdleModeOn, 0x39);
 
 #[derive(Debug, Copy, Clone)]
 pub struct ColumnAddressSet {
@@ -118,6 +164,81 @@ impl Command for PartialArea {
     }
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct VerticalScrollingDefinition {
+    /// Top fixed area lines
+    pub tfa: u16,
+    /// Vertical scrolling area lines
+    pub vsa: u16,
+    /// Bottom fixed area lines
+    pub bfa: u16,
+}
+
+impl Command for VerticalScrollingDefinition {
+    type Buffer = [u8; 6];
+
+    fn number() -> u8 {
+        0x33
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [(self.tfa >> 8) as u8,
+         self.tfa as u8,
+         (self.vsa >> 8) as u8,
+         self.vsa as u8,
+         (self.bfa >> 8) as u8,
+         self.bfa as u8,
+        ]
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct VerticalScrollingStartAddress {
+    /// Frame memory line to show at the top of the scrolling area
+    pub vsp: u16,
+}
+
+impl Command for VerticalScrollingStartAddress {
+    type Buffer = [u8; 2];
+
+    fn number() -> u8 {
+        0x37
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [(self.vsp >> 8) as u8,
+         self.vsp as u8,
+        ]
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(unused)]
+pub enum TearingEffectMode {
+    /// Signal V-Blanking only
+    VBlank = 0,
+    /// Signal both V-Blanking and H-Blanking
+    VHBlank = 1,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TearingEffectLineOn {
+    pub mode: TearingEffectMode,
+}
+
+impl Command for TearingEffectLineOn {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x35
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [self.mode as u8]
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct MemoryAccessControl {
     pub row_addr_order: bool,
     pub col_addr_order: bool,
@@ -152,7 +273,27 @@ impl Command for MemoryAccessControl {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Read Display MADCTL
+impl ReadCommand for MemoryAccessControl {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x0B
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        MemoryAccessControl {
+            row_addr_order: bit(buf[0], 7),
+            col_addr_order: bit(buf[0], 6),
+            row_col_exchange: bit(buf[0], 5),
+            vert_refresh_order: bit(buf[0], 4),
+            rgb_to_bgr: bit(buf[0], 3),
+            horiz_refresh_order: bit(buf[0], 2),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PixelFormat {
     Bpp16 = 0b101,
     Bpp18 = 0b110,
@@ -168,6 +309,26 @@ impl From<u8> for PixelFormat {
     }
 }
 
+impl PixelFormat {
+    /// Bytes transferred per pixel over SPI
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            PixelFormat::Bpp16 => 2,
+            PixelFormat::Bpp18 => 3,
+        }
+    }
+
+    /// Non-panicking conversion for values read back from the
+    /// controller
+    pub fn from_bits(x: u8) -> Option<Self> {
+        match x {
+            0b101 => Some(PixelFormat::Bpp16),
+            0b110 => Some(PixelFormat::Bpp18),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct InterfacePixelFormat {
     pub cpu_format: PixelFormat,
@@ -186,3 +347,762 @@ impl Command for InterfacePixelFormat {
          (self.rgb_format as u8)]
     }
 }
+
+#[derive(Debug, Copy, Clone)]
+pub struct SetTearScanline {
+    /// Line at which the TE output goes active
+    pub sts: u16,
+}
+
+impl Command for SetTearScanline {
+    type Buffer = [u8; 2];
+
+    fn number() -> u8 {
+        0x44
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [(self.sts >> 8) as u8,
+         self.sts as u8,
+        ]
+    }
+}
+
+/// Write (0x51) / Read (0x52) Display Brightness
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisplayBrightness(pub u8);
+
+impl Command for DisplayBrightness {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x51
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [self.0]
+    }
+}
+
+impl ReadCommand for DisplayBrightness {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x52
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        DisplayBrightness(buf[0])
+    }
+}
+
+/// Write (0x53) / Read (0x54) CTRL Display
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CtrlDisplay {
+    /// Brightness control block on
+    pub brightness_control: bool,
+    /// Fade when changing brightness
+    pub display_dimming: bool,
+    /// Backlight control on
+    pub backlight: bool,
+}
+
+impl Command for CtrlDisplay {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x53
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [if self.brightness_control { 1 << 5 } else { 0 } |
+         if self.display_dimming { 1 << 3 } else { 0 } |
+         if self.backlight { 1 << 2 } else { 0 }]
+    }
+}
+
+impl ReadCommand for CtrlDisplay {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x54
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        CtrlDisplay {
+            brightness_control: bit(buf[0], 5),
+            display_dimming: bit(buf[0], 3),
+            backlight: bit(buf[0], 2),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CabcMode {
+    Off = 0b00,
+    UserInterface = 0b01,
+    StillPicture = 0b10,
+    MovingImage = 0b11,
+}
+
+/// Write (0x55) / Read (0x56) Content Adaptive Brightness Control
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ContentAdaptiveBrightness {
+    pub mode: CabcMode,
+}
+
+impl Command for ContentAdaptiveBrightness {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x55
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [self.mode as u8]
+    }
+}
+
+impl ReadCommand for ContentAdaptiveBrightness {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x56
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        let mode = match buf[0] & 0b11 {
+            0b00 => CabcMode::Off,
+            0b01 => CabcMode::UserInterface,
+            0b10 => CabcMode::StillPicture,
+            _ => CabcMode::MovingImage,
+        };
+        ContentAdaptiveBrightness { mode }
+    }
+}
+
+/// Write (0x5E) / Read (0x5F) CABC Minimum Brightness
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CabcMinimumBrightness(pub u8);
+
+impl Command for CabcMinimumBrightness {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x5E
+    }
+
+    fn encode(self) -> Self::Buffer {
+        [self.0]
+    }
+}
+
+impl ReadCommand for CabcMinimumBrightness {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x5F
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        CabcMinimumBrightness(buf[0])
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PowerControl1 {
+    /// VREG1OUT voltage
+    pub vrh1: u8,
+    /// VREG2OUT voltage
+    pub vrh2: u8,
+}
+
+impl PowerControl1 {
+    pub const NUMBER: u8 = 0xC0;
+
+    /// `encode()` for constants such as the `init` tables
+    pub const fn params(self) -> [u8; 2] {
+        [self.vrh1 & 0x1F,
+         self.vrh2 & 0x1F,
+        ]
+    }
+}
+
+impl Command for PowerControl1 {
+    type Buffer = [u8; 2];
+
+    fn number() -> u8 {
+        Self::NUMBER
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.params()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PowerControl2 {
+    /// Source driver amplifier current
+    pub sap: u8,
+    /// Step-up factor
+    pub bt: u8,
+}
+
+impl PowerControl2 {
+    pub const NUMBER: u8 = 0xC1;
+
+    /// `encode()` for constants such as the `init` tables
+    pub const fn params(self) -> [u8; 1] {
+        [((self.sap & 0x7) << 4) | (self.bt & 0xF)]
+    }
+}
+
+impl Command for PowerControl2 {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        Self::NUMBER
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.params()
+    }
+}
+
+/// Power Control 3 (for Normal Mode)
+#[derive(Debug, Copy, Clone)]
+pub struct PowerControl3 {
+    /// Step-up circuit 2..5 frequency
+    pub dca1: u8,
+    /// Step-up circuit 1 frequency
+    pub dca0: u8,
+}
+
+impl PowerControl3 {
+    pub const NUMBER: u8 = 0xC2;
+
+    /// `encode()` for constants such as the `init` tables
+    pub const fn params(self) -> [u8; 1] {
+        [((self.dca1 & 0x7) << 4) | (self.dca0 & 0x7)]
+    }
+}
+
+impl Command for PowerControl3 {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        Self::NUMBER
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.params()
+    }
+}
+
+/// The writable parameters of VCOM Control. The fourth one,
+/// VCM_OUT, only reports the VCOM value in use.
+#[derive(Debug, Copy, Clone)]
+pub struct VcomControl {
+    /// Use VCOM value programmed in NV memory
+    pub nv_memory: bool,
+    /// VCOM voltage
+    pub vcm_reg: u8,
+    /// Select VCOM from `vcm_reg` instead of NV memory
+    pub vcm_reg_enable: bool,
+}
+
+impl VcomControl {
+    pub const NUMBER: u8 = 0xC5;
+
+    /// `encode()` for constants such as the `init` tables
+    pub const fn params(self) -> [u8; 3] {
+        [if self.nv_memory { 1 } else { 0 },
+         self.vcm_reg,
+         if self.vcm_reg_enable { 0x80 } else { 0 },
+        ]
+    }
+}
+
+impl Command for VcomControl {
+    type Buffer = [u8; 3];
+
+    fn number() -> u8 {
+        Self::NUMBER
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.params()
+    }
+}
+
+/// 15 gamma adjustment parameters
+#[derive(Debug, Copy, Clone)]
+pub struct PositiveGammaControl(pub [u8; 15]);
+
+impl Command for PositiveGammaControl {
+    type Buffer = [u8; 15];
+
+    fn number() -> u8 {
+        0xE0
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.0
+    }
+}
+
+/// 15 gamma adjustment parameters
+#[derive(Debug, Copy, Clone)]
+pub struct NegativeGammaControl(pub [u8; 15]);
+
+impl Command for NegativeGammaControl {
+    type Buffer = [u8; 15];
+
+    fn number() -> u8 {
+        0xE1
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.0
+    }
+}
+
+/// Digital gamma lookup table for the red channel
+#[allow(unused)]
+pub struct DigitalGammaControl1<'a>(pub &'a [u8]);
+
+impl<'a> Command for DigitalGammaControl1<'a> {
+    type Buffer = &'a [u8];
+
+    fn number() -> u8 {
+        0xE2
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.0
+    }
+}
+
+/// Digital gamma lookup table for the blue channel
+#[allow(unused)]
+pub struct DigitalGammaControl2<'a>(pub &'a [u8]);
+
+impl<'a> Command for DigitalGammaControl2<'a> {
+    type Buffer = &'a [u8];
+
+    fn number() -> u8 {
+        0xE3
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.0
+    }
+}
+
+#[inline(always)]
+fn bit(x: u8, bit: u8) -> bool {
+    x & (1 << bit) != 0
+}
+
+/// Read Display Identification Information
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisplayIdentification {
+    pub manufacturer_id: u8,
+    pub driver_version_id: u8,
+    pub driver_id: u8,
+}
+
+impl ReadCommand for DisplayIdentification {
+    type Buffer = [u8; 3];
+
+    fn number() -> u8 {
+        0x04
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        DisplayIdentification {
+            manufacturer_id: buf[0],
+            driver_version_id: buf[1],
+            driver_id: buf[2],
+        }
+    }
+}
+
+/// Read Display Status
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisplayStatus {
+    pub booster_on: bool,
+    pub madctl: MemoryAccessControl,
+    /// Raw interface color pixel format bits
+    pub pixel_format: u8,
+    pub idle_mode: bool,
+    pub partial_mode: bool,
+    pub sleep_out: bool,
+    pub normal_mode: bool,
+    pub vertical_scrolling: bool,
+    pub inversion: bool,
+    pub display_on: bool,
+    pub tearing_effect_line: bool,
+    /// Gamma curve selection
+    pub gamma_curve: u8,
+    /// Tearing effect line mode: V-Blank only (false), V-Blank and
+    /// H-Blank (true)
+    pub tearing_effect_mode: bool,
+}
+
+impl ReadCommand for DisplayStatus {
+    type Buffer = [u8; 4];
+
+    fn number() -> u8 {
+        0x09
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        DisplayStatus {
+            booster_on: bit(buf[0], 7),
+            madctl: MemoryAccessControl {
+                row_addr_order: bit(buf[0], 6),
+                col_addr_order: bit(buf[0], 5),
+                row_col_exchange: bit(buf[0], 4),
+                vert_refresh_order: bit(buf[0], 3),
+                rgb_to_bgr: bit(buf[0], 2),
+                horiz_refresh_order: bit(buf[0], 1),
+            },
+            pixel_format: (buf[1] >> 4) & 0b111,
+            idle_mode: bit(buf[1], 3),
+            partial_mode: bit(buf[1], 2),
+            sleep_out: bit(buf[1], 1),
+            normal_mode: bit(buf[1], 0),
+            vertical_scrolling: bit(buf[2], 7),
+            inversion: bit(buf[2], 5),
+            display_on: bit(buf[2], 2),
+            tearing_effect_line: bit(buf[2], 1),
+            gamma_curve: ((buf[2] & 1) << 2) | (buf[3] >> 6),
+            tearing_effect_mode: bit(buf[3], 5),
+        }
+    }
+}
+
+/// Read Display Power Mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PowerMode {
+    pub booster_on: bool,
+    pub idle_mode: bool,
+    pub partial_mode: bool,
+    pub sleep_out: bool,
+    pub normal_mode: bool,
+    pub display_on: bool,
+}
+
+impl ReadCommand for PowerMode {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x0A
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        PowerMode {
+            booster_on: bit(buf[0], 7),
+            idle_mode: bit(buf[0], 6),
+            partial_mode: bit(buf[0], 5),
+            sleep_out: bit(buf[0], 4),
+            normal_mode: bit(buf[0], 3),
+            display_on: bit(buf[0], 2),
+        }
+    }
+}
+
+/// Read Display Pixel Format
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisplayPixelFormat {
+    /// Raw RGB interface format bits
+    pub rgb_bits: u8,
+    /// Raw MCU interface format bits
+    pub cpu_bits: u8,
+}
+
+impl DisplayPixelFormat {
+    pub fn rgb_format(&self) -> Option<PixelFormat> {
+        PixelFormat::from_bits(self.rgb_bits)
+    }
+
+    pub fn cpu_format(&self) -> Option<PixelFormat> {
+        PixelFormat::from_bits(self.cpu_bits)
+    }
+}
+
+impl ReadCommand for DisplayPixelFormat {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x0C
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        DisplayPixelFormat {
+            rgb_bits: (buf[0] >> 4) & 0b111,
+            cpu_bits: buf[0] & 0b111,
+        }
+    }
+}
+
+/// Read Display Self-Diagnostic Result
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SelfDiagnostic {
+    pub register_loading: bool,
+    pub functionality: bool,
+}
+
+impl ReadCommand for SelfDiagnostic {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        0x0F
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        SelfDiagnostic {
+            register_loading: bit(buf[0], 7),
+            functionality: bit(buf[0], 6),
+        }
+    }
+}
+
+/// Read ID4
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Id4 {
+    pub ic_version: u8,
+    /// 0x9486 for the ILI9486
+    pub ic_model: u16,
+}
+
+impl ReadCommand for Id4 {
+    type Buffer = [u8; 3];
+
+    fn number() -> u8 {
+        0xD3
+    }
+
+    fn decode(buf: Self::Buffer) -> Self {
+        Id4 {
+            ic_version: buf[0],
+            ic_model: ((buf[1] as u16) << 8) | (buf[2] as u16),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Opcodes from the ILI9486 datasheet, chapter 8
+    fn opcode_table() -> [(&'static str, u8, u8); 47] {
+        [
+            ("DisplayIdentification", <DisplayIdentification as ReadCommand>::number(), 0x04),
+            ("DisplayStatus", <DisplayStatus as ReadCommand>::number(), 0x09),
+            ("PowerMode", <PowerMode as ReadCommand>::number(), 0x0A),
+            ("MemoryAccessControl (read)", <MemoryAccessControl as ReadCommand>::number(), 0x0B),
+            ("DisplayPixelFormat", <DisplayPixelFormat as ReadCommand>::number(), 0x0C),
+            ("SelfDiagnostic", <SelfDiagnostic as ReadCommand>::number(), 0x0F),
+            ("SleepIn", SleepIn::number(), 0x10),
+            ("SleepOut", SleepOut::number(), 0x11),
+            ("PartialModeOn", PartialModeOn::number(), 0x12),
+            ("NormalDisplayModeOn", NormalDisplayModeOn::number(), 0x13),
+            ("InversionOff", InversionOff::number(), 0x20),
+            ("InversionOn", InversionOn::number(), 0x21),
+            ("DisplayOff", DisplayOff::number(), 0x28),
+            ("DisplayOn", DisplayOn::number(), 0x29),
+            ("ColumnAddressSet", ColumnAddressSet::number(), 0x2A),
+            ("PageAddressSet", PageAddressSet::number(), 0x2B),
+            ("MemoryWrite", MemoryWrite::number(), 0x2C),
+            ("MemoryRead", MemoryRead::number(), 0x2E),
+            ("PartialArea", PartialArea::number(), 0x30),
+            ("VerticalScrollingDefinition", VerticalScrollingDefinition::number(), 0x33),
+            ("TearingEffectLineOff", TearingEffectLineOff::number(), 0x34),
+            ("TearingEffectLineOn", TearingEffectLineOn::number(), 0x35),
+            ("MemoryAccessControl", <MemoryAccessControl as Command>::number(), 0x36),
+            ("VerticalScrollingStartAddress", VerticalScrollingStartAddress::number(), 0x37),
+            ("IdleModeOff", IdleModeOff::number(), 0x38),
+            ("IdleModeOn", IdleModeOn::number(), 0x39),
+            ("InterfacePixelFormat", InterfacePixelFormat::number(), 0x3A),
+            ("MemoryWriteContinue", MemoryWriteContinue::number(), 0x3C),
+            ("MemoryReadContinue", MemoryReadContinue::number(), 0x3E),
+            ("SetTearScanline", SetTearScanline::number(), 0x44),
+            ("DisplayBrightness", <DisplayBrightness as Command>::number(), 0x51),
+            ("DisplayBrightness (read)", <DisplayBrightness as ReadCommand>::number(), 0x52),
+            ("CtrlDisplay", <CtrlDisplay as Command>::number(), 0x53),
+            ("CtrlDisplay (read)", <CtrlDisplay as ReadCommand>::number(), 0x54),
+            ("ContentAdaptiveBrightness", <ContentAdaptiveBrightness as Command>::number(), 0x55),
+            ("ContentAdaptiveBrightness (read)", <ContentAdaptiveBrightness as ReadCommand>::number(), 0x56),
+            ("CabcMinimumBrightness", <CabcMinimumBrightness as Command>::number(), 0x5E),
+            ("CabcMinimumBrightness (read)", <CabcMinimumBrightness as ReadCommand>::number(), 0x5F),
+            ("PowerControl1", PowerControl1::number(), 0xC0),
+            ("PowerControl2", PowerControl2::number(), 0xC1),
+            ("PowerControl3", PowerControl3::number(), 0xC2),
+            ("VcomControl", VcomControl::number(), 0xC5),
+            ("Id4", Id4::number(), 0xD3),
+            ("PositiveGammaControl", PositiveGammaControl::number(), 0xE0),
+            ("NegativeGammaControl", NegativeGammaControl::number(), 0xE1),
+            ("DigitalGammaControl1", DigitalGammaControl1::number(), 0xE2),
+            ("DigitalGammaControl2", DigitalGammaControl2::number(), 0xE3),
+        ]
+    }
+
+    #[test]
+    fn opcodes_match_datasheet() {
+        for &(name, number, expected) in opcode_table().iter() {
+            assert_eq!(number, expected, "{} opcode", name);
+        }
+    }
+
+    #[test]
+    fn opcodes_are_unique() {
+        let table = opcode_table();
+        for (i, &(name1, number1, _)) in table.iter().enumerate() {
+            for &(name2, number2, _) in table[(i + 1)..].iter() {
+                assert!(number1 != number2, "{} and {} share opcode {:02X}", name1, name2, number1);
+            }
+        }
+    }
+
+    #[test]
+    fn simple_commands_have_no_parameters() {
+        assert_eq!(SleepOut.encode().len(), 0);
+        assert_eq!(InversionOff.encode().len(), 0);
+        assert_eq!(IdleModeOn.encode().len(), 0);
+    }
+
+    #[test]
+    fn address_commands_encode_big_endian() {
+        assert_eq!(ColumnAddressSet { sc: 0x0012, ec: 0x013F }.encode(),
+                   [0x00, 0x12, 0x01, 0x3F]);
+        assert_eq!(PageAddressSet { sp: 0x0100, ep: 0x01DF }.encode(),
+                   [0x01, 0x00, 0x01, 0xDF]);
+        assert_eq!(PartialArea { sr: 0x0010, er: 0x0020 }.encode(),
+                   [0x00, 0x10, 0x00, 0x20]);
+        assert_eq!(VerticalScrollingDefinition { tfa: 0x10, vsa: 0x01C0, bfa: 0x10 }.encode(),
+                   [0x00, 0x10, 0x01, 0xC0, 0x00, 0x10]);
+        assert_eq!(VerticalScrollingStartAddress { vsp: 0x0123 }.encode(),
+                   [0x01, 0x23]);
+        assert_eq!(SetTearScanline { sts: 0x01DF }.encode(),
+                   [0x01, 0xDF]);
+    }
+
+    #[test]
+    fn memory_access_control_bits() {
+        let none = MemoryAccessControl {
+            row_addr_order: false,
+            col_addr_order: false,
+            row_col_exchange: false,
+            vert_refresh_order: false,
+            horiz_refresh_order: false,
+            rgb_to_bgr: false,
+        };
+        assert_eq!(none.encode(), [0x00]);
+        assert_eq!(MemoryAccessControl { row_addr_order: true, ..none }.encode(), [0x80]);
+        assert_eq!(MemoryAccessControl { col_addr_order: true, ..none }.encode(), [0x40]);
+        assert_eq!(MemoryAccessControl { row_col_exchange: true, ..none }.encode(), [0x20]);
+        assert_eq!(MemoryAccessControl { vert_refresh_order: true, ..none }.encode(), [0x10]);
+        assert_eq!(MemoryAccessControl { rgb_to_bgr: true, ..none }.encode(), [0x08]);
+        assert_eq!(MemoryAccessControl { horiz_refresh_order: true, ..none }.encode(), [0x04]);
+
+        let madctl = MemoryAccessControl { col_addr_order: true, rgb_to_bgr: true, ..none };
+        assert_eq!(<MemoryAccessControl as ReadCommand>::decode(madctl.encode()), madctl);
+    }
+
+    #[test]
+    fn interface_pixel_format() {
+        assert_eq!(InterfacePixelFormat {
+            cpu_format: PixelFormat::Bpp16,
+            rgb_format: PixelFormat::Bpp16,
+        }.encode(), [0x55]);
+        assert_eq!(InterfacePixelFormat {
+            cpu_format: PixelFormat::Bpp18,
+            rgb_format: PixelFormat::Bpp18,
+        }.encode(), [0x66]);
+
+        let read = DisplayPixelFormat::decode([0x65]);
+        assert_eq!(read.rgb_format(), Some(PixelFormat::Bpp18));
+        assert_eq!(read.cpu_format(), Some(PixelFormat::Bpp16));
+        assert_eq!(DisplayPixelFormat::decode([0x00]).cpu_format(), None);
+    }
+
+    #[test]
+    fn tearing_effect_line_on() {
+        assert_eq!(TearingEffectLineOn { mode: TearingEffectMode::VBlank }.encode(), [0x00]);
+        assert_eq!(TearingEffectLineOn { mode: TearingEffectMode::VHBlank }.encode(), [0x01]);
+    }
+
+    #[test]
+    fn power_and_vcom_control() {
+        assert_eq!(PowerControl1 { vrh1: 0x0E, vrh2: 0x0E }.encode(), [0x0E, 0x0E]);
+        assert_eq!(PowerControl2 { sap: 4, bt: 1 }.encode(), [0x41]);
+        assert_eq!(PowerControl3 { dca1: 4, dca0: 4 }.encode(), [0x44]);
+        assert_eq!(VcomControl { nv_memory: false, vcm_reg: 0x12, vcm_reg_enable: true }.encode(),
+                   [0x00, 0x12, 0x80]);
+    }
+
+    #[test]
+    fn gamma_control() {
+        let positive = [0x0F, 0x1F, 0x1C, 0x0C, 0x0F, 0x08, 0x48, 0x98,
+                        0x37, 0x0A, 0x13, 0x04, 0x11, 0x0D, 0x00];
+        let negative = [0x0F, 0x32, 0x2E, 0x0B, 0x0D, 0x05, 0x47, 0x75,
+                        0x37, 0x06, 0x10, 0x03, 0x24, 0x20, 0x00];
+        assert_eq!(PositiveGammaControl(positive).encode(), positive);
+        assert_eq!(NegativeGammaControl(negative).encode(), negative);
+        assert_eq!(super::super::gamma::WAVESHARE.positive_command().encode(), positive);
+        assert_eq!(super::super::gamma::WAVESHARE.negative_command().encode(), negative);
+
+        let lut: [u8; 16] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+                             0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF];
+        assert_eq!(DigitalGammaControl1(&lut).encode(), &lut[..]);
+        assert_eq!(DigitalGammaControl2(&lut[..4]).encode(), &[0x00, 0x11, 0x22, 0x33][..]);
+    }
+
+    #[test]
+    fn brightness_and_cabc() {
+        assert_eq!(DisplayBrightness(0x7F).encode(), [0x7F]);
+        assert_eq!(CtrlDisplay {
+            brightness_control: true,
+            display_dimming: true,
+            backlight: true,
+        }.encode(), [0x2C]);
+        assert_eq!(ContentAdaptiveBrightness { mode: CabcMode::StillPicture }.encode(), [0x02]);
+        assert_eq!(<ContentAdaptiveBrightness as ReadCommand>::decode([0x03]).mode,
+                   CabcMode::MovingImage);
+        assert_eq!(CabcMinimumBrightness(0x20).encode(), [0x20]);
+    }
+
+    #[test]
+    fn decode_status() {
+        let power = PowerMode::decode([0x9C]);
+        assert!(power.booster_on);
+        assert!(!power.idle_mode);
+        assert!(!power.partial_mode);
+        assert!(power.sleep_out);
+        assert!(power.normal_mode);
+        assert!(power.display_on);
+
+        let status = DisplayStatus::decode([0xA4, 0x53, 0x04, 0x00]);
+        assert!(status.booster_on);
+        assert!(status.madctl.col_addr_order);
+        assert!(status.madctl.rgb_to_bgr);
+        assert_eq!(status.pixel_format, 0b101);
+        assert!(status.sleep_out);
+        assert!(status.normal_mode);
+        assert!(status.display_on);
+        assert!(!status.tearing_effect_line);
+
+        let id = DisplayIdentification::decode([0x00, 0x94, 0x86]);
+        assert_eq!(id.driver_version_id, 0x94);
+        assert_eq!(id.driver_id, 0x86);
+
+        let id4 = Id4::decode([0x00, 0x94, 0x86]);
+        assert_eq!(id4.ic_model, 0x9486);
+
+        let diag = SelfDiagnostic::decode([0xC0]);
+        assert!(diag.register_loading);
+        assert!(diag.functionality);
+    }
+}
@@ -0,0 +1,46 @@
+//! Gamma correction presets for the Positive/Negative Gamma Control
+//! registers
+
+use super::command::{PositiveGammaControl, NegativeGammaControl};
+
+#[derive(Debug, Copy, Clone)]
+pub struct GammaCurve {
+    pub positive: [u8; 15],
+    pub negative: [u8; 15],
+}
+
+impl GammaCurve {
+    pub fn positive_command(&self) -> PositiveGammaControl {
+        PositiveGammaControl(self.positive)
+    }
+
+    pub fn negative_command(&self) -> NegativeGammaControl {
+        NegativeGammaControl(self.negative)
+    }
+}
+
+/// From the Waveshare 4" demo code
+pub const WAVESHARE: GammaCurve = GammaCurve {
+    positive: [0x0F, 0x1F, 0x1C, 0x0C, 0x0F, 0x08, 0x48, 0x98,
+               0x37, 0x0A, 0x13, 0x04, 0x11, 0x0D, 0x00],
+    negative: [0x0F, 0x32, 0x2E, 0x0B, 0x0D, 0x05, 0x47, 0x75,
+               0x37, 0x06, 0x10, 0x03, 0x24, 0x20, 0x00],
+};
+
+/// Slightly more saturated, from the MCUFRIEND library
+#[allow(unused)]
+pub const MCUFRIEND: GammaCurve = GammaCurve {
+    positive: [0x0F, 0x21, 0x1C, 0x0B, 0x0E, 0x08, 0x49, 0x98,
+               0x38, 0x09, 0x11, 0x03, 0x14, 0x10, 0x00],
+    negative: [0x0F, 0x2F, 0x2B, 0x0C, 0x0E, 0x06, 0x47, 0x76,
+               0x37, 0x07, 0x11, 0x04, 0x23, 0x1E, 0x00],
+};
+
+/// High contrast, from the Linux fbtft ili9486 driver
+#[allow(unused)]
+pub const FBTFT: GammaCurve = GammaCurve {
+    positive: [0x00, 0x2C, 0x2C, 0x0B, 0x0C, 0x04, 0x4C, 0x64,
+               0x36, 0x03, 0x0E, 0x01, 0x10, 0x01, 0x00],
+    negative: [0x0F, 0x37, 0x37, 0x0C, 0x0F, 0x05, 0x50, 0x32,
+               0x36, 0x04, 0x0B, 0x00, 0x19, 0x14, 0x0F],
+};
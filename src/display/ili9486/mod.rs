@@ -4,6 +4,7 @@ use embedded_hal::digital::OutputPin;
 
 pub mod command;
 use self::command::*;
+pub mod gamma;
 use super::super::spi::SpiDmaWrite;
 
 
@@ -25,8 +26,61 @@ impl<'a, SPI: SpiDmaWrite, DC: OutputPin, CS: OutputPin> Tft<'a, SPI, DC, CS> {
         result.map(move |_| TftWriter {
             spi: self.spi,
             cs: self.cs,
+            remaining: None,
+            window: None,
         })
     }
+
+    /// Send a read command and return a reader for the data that
+    /// follows the dummy read cycle
+    pub fn reader(mut self, reg: u8) -> Result<TftReader<'a, SPI, CS>, SPI::Error> {
+        let buf = [0, reg];
+
+        self.dc.set_low();
+        self.cs.set_low();
+        let result = self.spi.write_sync(buf)
+            .and_then(|_| {
+                self.dc.set_high();
+                // Dummy read cycle
+                self.spi.transfer(&mut [0])
+            });
+        self.dc.set_high();
+
+        match result {
+            Ok(()) =>
+                Ok(TftReader {
+                    spi: self.spi,
+                    cs: self.cs,
+                }),
+            Err(e) => {
+                self.cs.set_high();
+                Err(e)
+            }
+        }
+    }
+
+    /// Send a read command and decode the parameters returned
+    pub fn read_command<C: ReadCommand>(mut self) -> Result<C, SPI::Error> {
+        let buf = [0, C::number()];
+
+        self.dc.set_low();
+        self.cs.set_low();
+        let result = self.spi.write_sync(buf)
+            .and_then(|_| {
+                self.dc.set_high();
+                // Dummy read cycle
+                self.spi.transfer(&mut [0])
+            })
+            .and_then(|_| {
+                let mut buf = C::Buffer::default();
+                self.spi.transfer(buf.as_mut())
+                    .map(|_| C::decode(buf))
+            });
+        self.dc.set_high();
+        self.cs.set_high();
+
+        result
+    }
 }
 
 impl<'a, B: AsRef<[u8]>, SPI: SpiDmaWrite<DmaBuffer=B>, DC: OutputPin, CS: OutputPin> Tft<'a, SPI, DC, CS> {
@@ -41,12 +95,84 @@ impl<'a, B: AsRef<[u8]>, SPI: SpiDmaWrite<DmaBuffer=B>, DC: OutputPin, CS: Outpu
 pub struct TftWriter<'a, SPI: SpiDmaWrite, CS: OutputPin> {
     pub spi: SPI,
     pub cs: &'a mut CS,
+    /// Bytes left in the address window, if limited
+    remaining: Option<usize>,
+    /// Address window that the frame memory pointer belongs to, see
+    /// `for_window()`
+    window: Option<u32>,
 }
 
 impl<'a, SPI: SpiDmaWrite, CS: OutputPin> TftWriter<'a, SPI, CS> {
+    /// Expect exactly `bytes` more bytes
+    pub fn with_limit(mut self, bytes: usize) -> Self {
+        self.remaining = Some(bytes);
+        self
+    }
+
+    /// Tag the writer with the address window it writes to, so
+    /// that a `PausedWriter` can only be resumed within that window
+    pub fn for_window(mut self, window: u32) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Bytes left until the address window is filled
+    #[allow(unused)]
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining
+    }
+
     pub fn write(&mut self, buffer: SPI::DmaBuffer) -> Result<(), SPI::Error> {
+        if let Some(ref mut remaining) = self.remaining {
+            let len = buffer.as_ref().len();
+            assert!(len <= *remaining, "write exceeds address window");
+            *remaining -= len;
+        }
         self.spi.write_async(buffer)
     }
+
+    /// Send the 16bpp pixel `word` `count` times. Other pixel
+    /// formats can't be repeated by DMA, so this is only reachable
+    /// through `Display::fill_rect()`, which checks the format.
+    pub(crate) fn write_repeated_16bpp(&mut self, word: [u8; 2], count: usize) -> Result<(), SPI::Error> {
+        if let Some(ref mut remaining) = self.remaining {
+            assert!(2 * count <= *remaining, "write exceeds address window");
+            *remaining -= 2 * count;
+        }
+        self.spi.write_repeated(word, count)
+    }
+
+    /// Finish pending DMA and release the bus. The frame memory
+    /// pointer stays where it is so that the stream can be resumed
+    /// with `MemoryWriteContinue`.
+    #[allow(unused)]
+    pub fn pause(self) -> PausedWriter {
+        PausedWriter {
+            remaining: self.remaining,
+            window: self.window,
+        }
+    }
+}
+
+/// Token for a `TftWriter` that has been paused. Resuming is only
+/// valid if no other frame memory access or address window change
+/// happened in between.
+#[must_use]
+pub struct PausedWriter {
+    remaining: Option<usize>,
+    window: Option<u32>,
+}
+
+impl PausedWriter {
+    /// Bytes that were left in the address window
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining
+    }
+
+    /// Address window of the paused writer, if it was tagged
+    pub fn window(&self) -> Option<u32> {
+        self.window
+    }
 }
 
 impl<'a, SPI: SpiDmaWrite, CS: OutputPin> Drop for TftWriter<'a, SPI, CS> {
@@ -57,3 +183,25 @@ impl<'a, SPI: SpiDmaWrite, CS: OutputPin> Drop for TftWriter<'a, SPI, CS> {
         self.cs.set_high();
     }
 }
+
+
+pub struct TftReader<'a, SPI: SpiDmaWrite, CS: OutputPin> {
+    pub spi: SPI,
+    pub cs: &'a mut CS,
+}
+
+impl<'a, SPI: SpiDmaWrite, CS: OutputPin> TftReader<'a, SPI, CS> {
+    /// Fill `buffer` with the next bytes from the controller
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), SPI::Error> {
+        for b in buffer.iter_mut() {
+            *b = 0;
+        }
+        self.spi.transfer(buffer)
+    }
+}
+
+impl<'a, SPI: SpiDmaWrite, CS: OutputPin> Drop for TftReader<'a, SPI, CS> {
+    fn drop(&mut self) {
+        self.cs.set_high();
+    }
+}
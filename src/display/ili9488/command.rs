@@ -0,0 +1,62 @@
+//! Commands that only exist on the ILI9488. All other commands are
+//! shared with the ILI9486.
+
+pub use super::super::ili9486::command::*;
+
+#[derive(Debug, Copy, Clone)]
+pub struct SetImageFunction {
+    /// Accept 24-bit data on the DPI/DBI bus
+    pub enable_24bit_data: bool,
+}
+
+impl SetImageFunction {
+    pub const NUMBER: u8 = 0xE9;
+
+    /// `encode()` for constants such as `init::ILI9488`
+    pub const fn params(self) -> [u8; 1] {
+        [if self.enable_24bit_data { 1 } else { 0 }]
+    }
+}
+
+impl Command for SetImageFunction {
+    type Buffer = [u8; 1];
+
+    fn number() -> u8 {
+        Self::NUMBER
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.params()
+    }
+}
+
+/// Adjust Control 3, with the fixed values required by the
+/// datasheet and the DSI packet format selection
+#[derive(Debug, Copy, Clone)]
+pub struct AdjustControl3 {
+    /// Loosely packed 18-bit pixels over DSI
+    pub dsi_18bit_loose: bool,
+}
+
+impl AdjustControl3 {
+    pub const NUMBER: u8 = 0xF7;
+
+    /// `encode()` for constants such as `init::ILI9488`
+    pub const fn params(self) -> [u8; 4] {
+        [0xA9, 0x51, 0x2C,
+         if self.dsi_18bit_loose { 0x82 } else { 0x80 },
+        ]
+    }
+}
+
+impl Command for AdjustControl3 {
+    type Buffer = [u8; 4];
+
+    fn number() -> u8 {
+        Self::NUMBER
+    }
+
+    fn encode(self) -> Self::Buffer {
+        self.params()
+    }
+}
@@ -0,0 +1,7 @@
+//! http://www.lcdwiki.com/res/MSP3520/ILI9488%20Data%20Sheet.pdf
+//!
+//! Mostly command-compatible with the ILI9486, so its transport and
+//! the shared commands are used as they are. Only the commands that
+//! differ live here. The ILI9488 only accepts 18bpp pixels over SPI.
+
+pub mod command;
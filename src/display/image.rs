@@ -0,0 +1,37 @@
+use super::Rect;
+
+/// RGB565 image with 2 bytes per pixel in the byte order of
+/// `rgb_to_16bpp()`, usually stored in flash
+#[derive(Debug, Clone, Copy)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub data: &'static [u8],
+}
+
+impl Image {
+    #[allow(unused)]
+    pub fn new(width: usize, height: usize, data: &'static [u8]) -> Self {
+        assert_eq!(data.len(), 2 * width * height, "image data size");
+        Image { width, height, data }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// Bytes of row `y` from column `x` on
+    pub fn row(&self, x: usize, y: usize, width: usize) -> &'static [u8] {
+        let start = 2 * (y * self.width + x);
+        &self.data[start..(start + 2 * width)]
+    }
+
+    /// Expand a pixel to 8 bits per channel
+    pub fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        let i = 2 * (y * self.width + x);
+        let (hi, lo) = (self.data[i], self.data[i + 1]);
+        (hi & 0xF8,
+         ((hi & 0x07) << 5) | ((lo & 0xE0) >> 3),
+         lo << 3)
+    }
+}
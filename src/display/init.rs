@@ -0,0 +1,161 @@
+//! Initialisation sequences for different panel revisions. The
+//! driver takes care of reset, Sleep Out and Display On, and sends
+//! MADCTL and COLMOD itself after the sequence.
+
+use super::Controller;
+use super::ili9486::gamma;
+use super::ili9486::command::{PowerControl1, PowerControl2, PowerControl3, VcomControl};
+use super::ili9488::command::{SetImageFunction, AdjustControl3};
+
+/// A command with raw parameters, followed by a delay
+#[derive(Debug, Clone, Copy)]
+pub struct InitStep {
+    pub command: u8,
+    pub params: &'static [u8],
+    pub delay_ms: u16,
+}
+
+/// Steps for one controller
+#[derive(Debug)]
+pub struct InitSequence {
+    /// Controller that the steps are written for, `None` if they
+    /// suit any
+    pub controller: Option<Controller>,
+    pub steps: &'static [InitStep],
+}
+
+macro_rules! step {
+    ($command: expr, $params: expr) => (
+        step!($command, $params, 0)
+    );
+    ($command: expr, $params: expr, $delay_ms: expr) => (
+        InitStep {
+            command: $command,
+            params: $params,
+            delay_ms: $delay_ms,
+        }
+    );
+}
+
+/// Controller defaults
+#[allow(unused)]
+pub static BASIC: &InitSequence = &InitSequence {
+    controller: None,
+    steps: &[],
+};
+
+/// Power and gamma setup from the Waveshare demo code
+pub static WAVESHARE: &InitSequence = &InitSequence {
+    controller: Some(Controller::Ili9486),
+    steps: &[
+        // Power Control 1..3. The demo code sends an extra 0x00 for
+        // Power Control 2, which has a single parameter.
+        step!(PowerControl1::NUMBER, &PowerControl1 { vrh1: 0x19, vrh2: 0x1A }.params()),
+        step!(PowerControl2::NUMBER, &PowerControl2 { sap: 4, bt: 5 }.params()),
+        step!(PowerControl3::NUMBER, &PowerControl3 { dca1: 3, dca0: 3 }.params()),
+        step!(VcomControl::NUMBER, &VcomControl {
+            nv_memory: false,
+            vcm_reg: 0x28,
+            vcm_reg_enable: false,
+        }.params()),
+        // Frame Rate Control
+        step!(0xB1, &[0xA0, 0x11]),
+        // Display Inversion Control
+        step!(0xB4, &[0x02]),
+        // Display Function Control
+        step!(0xB6, &[0x00, 0x42, 0x3B]),
+        // Positive/Negative Gamma Control
+        step!(0xE0, &gamma::WAVESHARE.positive),
+        step!(0xE1, &gamma::WAVESHARE.negative),
+    ],
+};
+
+/// Setup of the Linux fbtft driver for later Waveshare boards
+#[allow(unused)]
+pub static FBTFT: &InitSequence = &InitSequence {
+    controller: Some(Controller::Ili9486),
+    steps: &[
+        // Interface Mode Control
+        step!(0xB0, &[0x00]),
+        step!(PowerControl3::NUMBER, &PowerControl3 { dca1: 4, dca0: 4 }.params()),
+        // fbtft also sends a zero for the read-only VCM_OUT
+        step!(VcomControl::NUMBER, &VcomControl {
+            nv_memory: false,
+            vcm_reg: 0,
+            vcm_reg_enable: false,
+        }.params()),
+        // Positive/Negative Gamma Control
+        step!(0xE0, &gamma::FBTFT.positive),
+        step!(0xE1, &gamma::FBTFT.negative),
+    ],
+};
+
+/// ILI9488 based shields
+pub static ILI9488: &InitSequence = &InitSequence {
+    controller: Some(Controller::Ili9488),
+    steps: &[
+        // Positive/Negative Gamma Control
+        step!(0xE0, &[0x00, 0x03, 0x09, 0x08, 0x16, 0x0A, 0x3F, 0x78,
+                      0x4C, 0x09, 0x0A, 0x08, 0x16, 0x1A, 0x0F]),
+        step!(0xE1, &[0x00, 0x16, 0x19, 0x03, 0x0F, 0x05, 0x32, 0x45,
+                      0x46, 0x04, 0x0E, 0x0D, 0x35, 0x37, 0x0F]),
+        step!(PowerControl1::NUMBER, &PowerControl1 { vrh1: 0x17, vrh2: 0x15 }.params()),
+        step!(PowerControl2::NUMBER, &PowerControl2 { sap: 4, bt: 1 }.params()),
+        step!(VcomControl::NUMBER, &VcomControl {
+            nv_memory: false,
+            vcm_reg: 0x12,
+            vcm_reg_enable: true,
+        }.params()),
+        // Interface Mode Control
+        step!(0xB0, &[0x00]),
+        // Frame Rate Control
+        step!(0xB1, &[0xA0]),
+        // Display Inversion Control
+        step!(0xB4, &[0x02]),
+        // Display Function Control
+        step!(0xB6, &[0x02, 0x02, 0x3B]),
+        step!(SetImageFunction::NUMBER, &SetImageFunction {
+            enable_24bit_data: false,
+        }.params()),
+        step!(AdjustControl3::NUMBER, &AdjustControl3 {
+            dsi_18bit_loose: true,
+        }.params()),
+    ],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(init: &InitSequence, command: u8) -> Option<&'static [u8]> {
+        init.steps.iter()
+            .find(|step| step.command == command)
+            .map(|step| step.params)
+    }
+
+    #[test]
+    fn ili9486_typed_commands() {
+        assert_eq!(params(WAVESHARE, 0xC0), Some(&[0x19, 0x1A][..]));
+        assert_eq!(params(WAVESHARE, 0xC1), Some(&[0x45][..]));
+        assert_eq!(params(WAVESHARE, 0xC2), Some(&[0x33][..]));
+        assert_eq!(params(WAVESHARE, 0xC5), Some(&[0x00, 0x28, 0x00][..]));
+        assert_eq!(params(FBTFT, 0xC2), Some(&[0x44][..]));
+        assert_eq!(params(FBTFT, 0xC5), Some(&[0x00, 0x00, 0x00][..]));
+    }
+
+    #[test]
+    fn ili9488_typed_commands() {
+        assert_eq!(params(ILI9488, 0xC0), Some(&[0x17, 0x15][..]));
+        assert_eq!(params(ILI9488, 0xC1), Some(&[0x41][..]));
+        assert_eq!(params(ILI9488, 0xC5), Some(&[0x00, 0x12, 0x80][..]));
+        assert_eq!(params(ILI9488, 0xE9), Some(&[0x00][..]));
+        assert_eq!(params(ILI9488, 0xF7), Some(&[0xA9, 0x51, 0x2C, 0x82][..]));
+    }
+
+    #[test]
+    fn default_init_matches_controller() {
+        for &controller in &[Controller::Ili9486, Controller::Ili9488] {
+            assert_eq!(controller.default_init().controller, Some(controller));
+        }
+    }
+}
@@ -0,0 +1,397 @@
+//! Z-ordered stack of pixel sources that are combined per scanline
+
+use super::console::Console;
+use super::font::{Font, Text, MAX_COVERAGE};
+use super::image::Image;
+use super::shapes::{Shape, Spans};
+
+/// Layers that a `Compositor` can hold
+const MAX_LAYERS: usize = 8;
+
+/// How a layer combines with the layers below
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blend {
+    /// Covers all layers below, which are not evaluated
+    Opaque,
+    /// Pixels of this colour let the layers below show through
+    #[allow(unused)]
+    ColorKey((u8, u8, u8)),
+    /// Mixed with the layers below by this opacity, multiplied
+    /// with `Layer::alpha()`
+    Alpha(u8),
+}
+
+/// Source of pixels in the coordinates passed to `DirtyRegion::render()`
+pub trait Layer {
+    /// Called before the pixels of row `y`, to prepare e.g. shape
+    /// spans
+    fn start_row(&mut self, _y: usize) {}
+    fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8);
+    /// Opacity from 0 to 255. Zero is transparent with any `Blend`
+    /// except `Opaque`.
+    fn alpha(&self, _x: usize, _y: usize) -> u8 {
+        255
+    }
+}
+
+/// Closures `|x, y| (r, g, b)`
+impl<F: Fn(usize, usize) -> (u8, u8, u8)> Layer for F {
+    fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        self(x, y)
+    }
+}
+
+/// A shape in a single colour, transparent elsewhere
+pub struct ShapeLayer<S> {
+    pub shape: S,
+    pub color: (u8, u8, u8),
+    spans: Spans,
+}
+
+impl<S: Shape> ShapeLayer<S> {
+    pub fn new(shape: S, color: (u8, u8, u8)) -> Self {
+        ShapeLayer {
+            shape, color,
+            spans: Spans::new(),
+        }
+    }
+}
+
+impl<S: Shape> Layer for ShapeLayer<S> {
+    fn start_row(&mut self, y: usize) {
+        self.spans.clear();
+        self.shape.spans(y, &mut self.spans);
+    }
+
+    fn get_pixel(&self, _x: usize, _y: usize) -> (u8, u8, u8) {
+        self.color
+    }
+
+    fn alpha(&self, x: usize, _y: usize) -> u8 {
+        if self.spans.contains(x) {
+            255
+        } else {
+            0
+        }
+    }
+}
+
+/// Text in a single colour, with its anti-aliasing as opacity
+pub struct TextLayer<'a, F> {
+    pub text: Text<'a, F>,
+    pub color: (u8, u8, u8),
+}
+
+impl<'a, F: Font> TextLayer<'a, F> {
+    pub fn new(text: Text<'a, F>, color: (u8, u8, u8)) -> Self {
+        TextLayer { text, color }
+    }
+}
+
+impl<'a, F: Font> Layer for TextLayer<'a, F> {
+    fn get_pixel(&self, _x: usize, _y: usize) -> (u8, u8, u8) {
+        self.color
+    }
+
+    fn alpha(&self, x: usize, y: usize) -> u8 {
+        coverage_to_alpha(self.text.coverage(x, y))
+    }
+}
+
+fn coverage_to_alpha(coverage: u8) -> u8 {
+    (coverage as u16 * 255 / MAX_COVERAGE as u16) as u8
+}
+
+/// Anti-aliased `Console` text in a single colour
+pub struct ConsoleLayer<'a> {
+    pub console: &'a Console,
+    /// Whether the console is shown with `Display::scroll_to()`,
+    /// see `Console::take_dirty()`
+    pub hardware_scroll: bool,
+    pub color: (u8, u8, u8),
+}
+
+impl<'a> Layer for ConsoleLayer<'a> {
+    fn get_pixel(&self, _x: usize, _y: usize) -> (u8, u8, u8) {
+        self.color
+    }
+
+    fn alpha(&self, x: usize, y: usize) -> u8 {
+        coverage_to_alpha(if self.hardware_scroll {
+            self.console.coverage(x, y)
+        } else {
+            self.console.screen_coverage(x, y)
+        })
+    }
+}
+
+/// An `Image` placed at `(x, y)`, transparent elsewhere. Combine
+/// with `Blend::ColorKey` for sprites.
+pub struct ImageLayer {
+    pub image: Image,
+    pub x: usize,
+    pub y: usize,
+}
+
+impl ImageLayer {
+    #[allow(unused)]
+    pub fn new(image: Image, x: usize, y: usize) -> Self {
+        ImageLayer { image, x, y }
+    }
+
+    fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y &&
+            self.image.bounds().contains(x - self.x, y - self.y)
+    }
+}
+
+impl Layer for ImageLayer {
+    fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        if self.contains(x, y) {
+            self.image.get_pixel(x - self.x, y - self.y)
+        } else {
+            (0, 0, 0)
+        }
+    }
+
+    fn alpha(&self, x: usize, y: usize) -> u8 {
+        if self.contains(x, y) {
+            255
+        } else {
+            0
+        }
+    }
+}
+
+/// Handle to remove a layer again. Never reused, so it is only
+/// valid until that layer is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerId(u32);
+
+/// Combines layers bottom to top. Adding or removing layers does
+/// not invalidate anything, the caller must do that for their
+/// areas.
+pub struct Compositor<'a> {
+    /// Bottom to top without gaps
+    layers: [Option<(LayerId, &'a mut dyn Layer, Blend)>; MAX_LAYERS],
+    /// Id of the next pushed layer
+    next_id: u32,
+    /// Row that the layers were last prepared for
+    row: Option<usize>,
+    /// Topmost `Blend::Opaque` layer, where evaluation starts
+    base: usize,
+}
+
+impl<'a> Compositor<'a> {
+    pub fn new() -> Self {
+        Compositor {
+            layers: [None, None, None, None, None, None, None, None],
+            next_id: 0,
+            row: None,
+            base: 0,
+        }
+    }
+
+    fn position(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter()
+            .position(|layer| match *layer {
+                Some((layer_id, _, _)) => layer_id == id,
+                None => false,
+            })
+    }
+
+    /// Place `layer` on top, or give `None` when full
+    pub fn push(&mut self, layer: &'a mut dyn Layer, blend: Blend) -> Option<LayerId> {
+        let top = self.layers.iter()
+            .position(|layer| layer.is_none())?;
+        let id = LayerId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.layers[top] = Some((id, layer, blend));
+        self.row = None;
+        Some(id)
+    }
+
+    /// Take out the layer of `id`, or `None` if it is already gone.
+    /// The layers above move down.
+    #[allow(unused)]
+    pub fn remove(&mut self, id: LayerId) -> Option<&'a mut dyn Layer> {
+        let i = self.position(id)?;
+        let (_, layer, _) = self.layers[i].take()?;
+        self.layers[i..].rotate_left(1);
+        self.row = None;
+        Some(layer)
+    }
+
+    /// Change how the layer of `id` combines, ignored if it is
+    /// already gone
+    #[allow(unused)]
+    pub fn set_blend(&mut self, id: LayerId, blend: Blend) {
+        if let Some(i) = self.position(id) {
+            if let Some((_, _, ref mut old_blend)) = self.layers[i] {
+                *old_blend = blend;
+            }
+            self.row = None;
+        }
+    }
+
+    fn start_row(&mut self, y: usize) {
+        self.base = self.layers.iter()
+            .rposition(|layer| matches!(*layer, Some((_, _, Blend::Opaque))))
+            .unwrap_or(0);
+        for layer in self.layers[self.base..].iter_mut() {
+            if let Some((_, ref mut layer, _)) = *layer {
+                layer.start_row(y);
+            }
+        }
+        self.row = Some(y);
+    }
+
+    /// Combined colour at `(x, y)`, black where no layer is opaque
+    pub fn get_pixel(&mut self, x: usize, y: usize) -> (u8, u8, u8) {
+        if self.row != Some(y) {
+            self.start_row(y);
+        }
+
+        let mut pixel = (0, 0, 0);
+        for layer in self.layers[self.base..].iter() {
+            let (layer, blend) = match *layer {
+                Some((_, ref layer, blend)) => (layer, blend),
+                None => continue,
+            };
+            pixel = match blend {
+                Blend::Opaque =>
+                    layer.get_pixel(x, y),
+                Blend::ColorKey(_) if layer.alpha(x, y) == 0 =>
+                    pixel,
+                Blend::ColorKey(key) => {
+                    let color = layer.get_pixel(x, y);
+                    if color == key {
+                        pixel
+                    } else {
+                        color
+                    }
+                }
+                Blend::Alpha(opacity) => {
+                    let alpha = opacity as u16 * layer.alpha(x, y) as u16 / 255;
+                    match alpha {
+                        0 => pixel,
+                        255 => layer.get_pixel(x, y),
+                        _ => mix(pixel, layer.get_pixel(x, y), alpha),
+                    }
+                }
+            };
+        }
+        pixel
+    }
+}
+
+fn mix(below: (u8, u8, u8), above: (u8, u8, u8), alpha: u16) -> (u8, u8, u8) {
+    let mix = |below: u8, above: u8| {
+        ((below as u16 * (255 - alpha) + above as u16 * alpha) / 255) as u8
+    };
+    (mix(below.0, above.0),
+     mix(below.1, above.1),
+     mix(below.2, above.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::cell::Cell;
+
+    fn solid(color: (u8, u8, u8)) -> impl Fn(usize, usize) -> (u8, u8, u8) {
+        move |_x, _y| color
+    }
+
+    #[test]
+    fn stale_ids_are_ignored() {
+        let mut red = solid((255, 0, 0));
+        let mut green = solid((0, 255, 0));
+        let mut blue = solid((0, 0, 255));
+        let mut layers = Compositor::new();
+        layers.push(&mut red, Blend::Opaque).unwrap();
+        let old = layers.push(&mut green, Blend::Opaque).unwrap();
+        assert!(layers.remove(old).is_some());
+        let new = layers.push(&mut blue, Blend::Opaque).unwrap();
+        assert_ne!(old, new);
+
+        layers.set_blend(old, Blend::Alpha(0));
+        assert_eq!(layers.get_pixel(0, 0), (0, 0, 255));
+        assert!(layers.remove(old).is_none());
+        assert_eq!(layers.get_pixel(0, 0), (0, 0, 255));
+
+        layers.set_blend(new, Blend::Alpha(0));
+        assert_eq!(layers.get_pixel(0, 1), (255, 0, 0));
+        assert!(layers.remove(new).is_some());
+        assert!(layers.remove(new).is_none());
+    }
+
+    #[test]
+    fn removed_slots_are_reused() {
+        let mut fills: Vec<_> = (0..MAX_LAYERS + 1)
+            .map(|i| solid((i as u8, 0, 0)))
+            .collect();
+        let (last, fills) = fills.split_last_mut().unwrap();
+        let mut layers = Compositor::new();
+        let ids: Vec<_> = fills.iter_mut()
+            .map(|fill| layers.push(fill, Blend::Opaque).unwrap())
+            .collect();
+        let mut extra = solid((99, 0, 0));
+        assert!(layers.push(&mut extra, Blend::Opaque).is_none());
+
+        // Freeing a lower layer makes room on top
+        assert!(layers.remove(ids[1]).is_some());
+        layers.push(last, Blend::Opaque).unwrap();
+        assert_eq!(layers.get_pixel(0, 0), (MAX_LAYERS as u8, 0, 0));
+        layers.set_blend(ids[2], Blend::Alpha(0));
+        assert!(layers.remove(ids[7]).is_some());
+        assert_eq!(layers.get_pixel(0, 1), (MAX_LAYERS as u8, 0, 0));
+    }
+
+    #[test]
+    fn color_key_passes_through() {
+        let mut below = solid((10, 20, 30));
+        let mut sprite = |x: usize, _y: usize| if x == 0 { (255, 0, 255) } else { (1, 2, 3) };
+        let mut layers = Compositor::new();
+        layers.push(&mut below, Blend::Opaque).unwrap();
+        layers.push(&mut sprite, Blend::ColorKey((255, 0, 255))).unwrap();
+        assert_eq!(layers.get_pixel(0, 0), (10, 20, 30));
+        assert_eq!(layers.get_pixel(1, 0), (1, 2, 3));
+    }
+
+    #[test]
+    fn alpha_mixes() {
+        assert_eq!(mix((0, 100, 255), (255, 200, 0), 0), (0, 100, 255));
+        assert_eq!(mix((0, 100, 255), (255, 200, 0), 255), (255, 200, 0));
+        assert_eq!(mix((0, 100, 255), (255, 200, 0), 51), (51, 120, 204));
+
+        let mut below = solid((0, 0, 0));
+        let mut above = solid((255, 255, 255));
+        let mut layers = Compositor::new();
+        layers.push(&mut below, Blend::Opaque).unwrap();
+        let id = layers.push(&mut above, Blend::Alpha(102)).unwrap();
+        assert_eq!(layers.get_pixel(0, 0), (102, 102, 102));
+        layers.set_blend(id, Blend::Alpha(0));
+        assert_eq!(layers.get_pixel(0, 0), (0, 0, 0));
+    }
+
+    #[test]
+    fn layers_below_opaque_are_skipped() {
+        let calls = Cell::new(0);
+        let mut hidden = |_x: usize, _y: usize| {
+            calls.set(calls.get() + 1);
+            (1, 1, 1)
+        };
+        let mut cover = solid((7, 7, 7));
+        let mut layers = Compositor::new();
+        layers.push(&mut hidden, Blend::Opaque).unwrap();
+        let id = layers.push(&mut cover, Blend::Opaque).unwrap();
+        assert_eq!(layers.get_pixel(0, 0), (7, 7, 7));
+        assert_eq!(calls.get(), 0);
+
+        layers.set_blend(id, Blend::Alpha(255));
+        assert_eq!(layers.get_pixel(0, 0), (7, 7, 7));
+        assert_eq!(calls.get(), 1);
+    }
+}
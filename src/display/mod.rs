@@ -1,5 +1,6 @@
 //! https://www.waveshare.com/w/upload/7/78/ILI9486_Datasheet.pdf
 
+use core::fmt;
 use core::mem::replace;
 
 use embedded_hal::{
@@ -13,7 +14,7 @@ use embedded_hal::{
         Phase as SpiPhase,
     },
     blocking::{
-        delay::DelayMs,
+        delay::{DelayMs, DelayUs},
         spi::{
             Transfer as SpiTransfer,
             Write as SpiWrite,
@@ -27,7 +28,7 @@ use stm32f429_hal::{
     dma::Transfer,
     time::U32Ext,
     gpio::{
-        gpiof::{PF13, PF14},
+        gpiof::{PF12, PF13, PF14},
         gpiod::PD14,
         gpioe::{PE9, PE11, PE13},
         Input, Output, Floating, PushPull,
@@ -36,18 +37,41 @@ use stm32f429_hal::{
 
 use super::spi::SpiDmaWrite;
 pub mod xpt2046;
-use self::xpt2046::Ts;
+use self::xpt2046::{Ts, Calibration};
 pub mod ili9486;
+pub mod ili9488;
 use self::ili9486::{
-    command::{self, Command},
-    Tft, TftWriter
+    command::{self, Command, ReadCommand, PixelFormat, Id4, DisplayIdentification},
+    gamma::GammaCurve,
+    Tft, TftWriter, TftReader, PausedWriter,
 };
 pub mod console;
+pub mod decode;
+pub mod dirty;
+pub mod font;
+pub mod graphics;
+pub mod init;
+pub mod layer;
+use self::init::InitSequence;
+pub mod rect;
+pub use self::rect::Rect;
+pub mod image;
+use self::image::Image;
+pub mod orientation;
+use self::orientation::{Orientation, Rotation};
+pub mod tearing;
+pub mod screenshot;
+pub mod shapes;
+use self::tearing::TearingEffect;
 mod scanline;
+mod dma_repeat;
 pub use self::scanline::ScanLine;
 
+/// Native (portrait) panel size
 pub const WIDTH: usize = 320;
 pub const HEIGHT: usize = 480;
+/// Longest line in any orientation
+pub const MAX_WIDTH: usize = HEIGHT;
 
 
 #[inline(always)]
@@ -56,12 +80,27 @@ pub fn rgb_to_16bpp(r: u8, g: u8, b: u8) -> [u8; 2] {
      ((g & 0x1C) << 3) | (b >> 3)]
 }
 
-#[allow(unused)]
 #[inline(always)]
 pub fn rgb_to_18bpp(r: u8, g: u8, b: u8) -> [u8; 3] {
     [r, g, b]
 }
 
+/// Write one pixel in `format` to the start of `buf`, returning the
+/// number of bytes used
+#[inline(always)]
+pub fn write_rgb(format: PixelFormat, buf: &mut [u8], r: u8, g: u8, b: u8) -> usize {
+    match format {
+        PixelFormat::Bpp16 => {
+            buf[..2].copy_from_slice(&rgb_to_16bpp(r, g, b));
+            2
+        }
+        PixelFormat::Bpp18 => {
+            buf[..3].copy_from_slice(&rgb_to_18bpp(r, g, b));
+            3
+        }
+    }
+}
+
 
 /// TODO: use MODE0 after next embedded_hal release
 fn spi_mode0() -> SpiMode {
@@ -111,6 +150,8 @@ mod spi1 {
     pub enum Target {
         /// TFT controller
         Tft,
+        /// TFT controller, slower clock for reading
+        TftRead,
         /// Touch screen
         Ts,
         /// SD card slot
@@ -122,6 +163,7 @@ mod spi1 {
         pub fn mhz(&self) -> u32 {
             match *self {
                 Target::Tft => 12,
+                Target::TftRead => 6,
                 Target::Ts => 2,
                 Target::Sd => 8,
             }
@@ -133,18 +175,147 @@ mod spi1 {
     }
 }
 
+/// Supported TFT controllers
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Controller {
+    Ili9486,
+    Ili9488,
+}
+
+impl Controller {
+    /// Identify the controller from its ID registers
+    pub fn detect(id4: &Id4, display_id: &DisplayIdentification) -> Option<Self> {
+        match (id4.ic_model, display_id.driver_version_id, display_id.driver_id) {
+            (0x9486, _, _) | (_, 0x94, 0x86) => Some(Controller::Ili9486),
+            (0x9488, _, _) | (_, 0x94, 0x88) => Some(Controller::Ili9488),
+            _ => None,
+        }
+    }
+
+    /// Initialisation sequence to use if none is given
+    pub fn default_init(&self) -> &'static InitSequence {
+        match *self {
+            Controller::Ili9486 => init::WAVESHARE,
+            Controller::Ili9488 => init::ILI9488,
+        }
+    }
+
+    /// Closest pixel format that the controller accepts over SPI
+    pub fn pixel_format(&self, requested: PixelFormat) -> PixelFormat {
+        match *self {
+            Controller::Ili9486 => requested,
+            Controller::Ili9488 => PixelFormat::Bpp18,
+        }
+    }
+}
+
+/// How `Display::new()` sets up the controller
+#[derive(Debug, Copy, Clone)]
+pub struct DisplayConfig {
+    /// Detected from the ID registers if not given
+    pub controller: Option<Controller>,
+    /// Requested format, adjusted to what the controller accepts
+    pub pixel_format: PixelFormat,
+    /// `Controller::default_init()` if not given
+    pub init: Option<&'static InitSequence>,
+    /// Assumed instead of failing when detection finds no supported
+    /// controller
+    pub fallback: Option<Controller>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            controller: None,
+            pixel_format: PixelFormat::Bpp16,
+            init: None,
+            fallback: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InitError {
+    Spi(Error),
+    /// Neither ID register matched a supported controller
+    UnknownController {
+        id4: Id4,
+        display_id: DisplayIdentification,
+    },
+    /// The initialisation sequence is written for another controller
+    InitMismatch {
+        controller: Controller,
+        init: Controller,
+    },
+}
+
+impl From<Error> for InitError {
+    fn from(e: Error) -> Self {
+        InitError::Spi(e)
+    }
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InitError::Spi(ref e) =>
+                write!(f, "SPI error: {:?}", e),
+            InitError::UnknownController { ref id4, ref display_id } =>
+                write!(f, "Unknown TFT controller: ID4 {:02X}/{:04X}, display ID {:02X}/{:02X}/{:02X}",
+                       id4.ic_version, id4.ic_model,
+                       display_id.manufacturer_id, display_id.driver_version_id, display_id.driver_id),
+            InitError::InitMismatch { controller, init } =>
+                write!(f, "Initialisation sequence for {:?} on {:?}", init, controller),
+        }
+    }
+}
+
+/// Sleep state of the controller, see `Display::sleep()`/`wake()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PowerState {
+    /// After hardware reset, in sleep mode
+    Reset,
+    /// Out of sleep, display output still off
+    SleepOut,
+    On,
+    /// Sleep mode entered with `Display::sleep()`
+    Sleep,
+}
+
+/// Partial and idle mode of the controller
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisplayMode {
+    /// Only show frame memory lines `start..=end`, leaving the rest
+    /// of the panel black
+    pub partial: Option<(u16, u16)>,
+    /// Reduce colour depth to 8 colours
+    pub idle: bool,
+}
+
+impl DisplayMode {
+    pub const NORMAL: DisplayMode = DisplayMode {
+        partial: None,
+        idle: false,
+    };
+}
+
+type TftRst = PF12<Output<PushPull>>;
 type TftDc = PF13<Output<PushPull>>;
 type TftCs = PD14<Output<PushPull>>;
 type TsPen = PE13<Input<Floating>>;
 type TsBusy = PE9<Input<Floating>>;
 type TsCs = PF14<Output<PushPull>>;
 type SdCs = PE11<Output<PushPull>>;
+/// Pixel writer for the area set up by `Display::write_area()`
+type AreaWriter<'a, B> = TftWriter<'a, DisplaySpi<'a, B>, TftCs>;
 
 pub struct Display {
     spi_state: spi1::State,
     spi_dma_stream: Option<spi1::DmaStream>,
     apb2: APB2,
     clocks: Clocks,
+    /// Reset Pin
+    tft_rst: TftRst,
     /// Data/Command Select Pin
     tft_dc: TftDc,
     /// Chip Select
@@ -157,51 +328,353 @@ pub struct Display {
     ts_cs: TsCs,
     /// Chip Select
     sd_cs: SdCs,
+    /// Hardware scrolling area: (top fixed lines, scrolling lines)
+    scroll_region: (u16, u16),
+    /// Vertical Scrolling Pointer: frame memory line shown at the
+    /// top of the scrolling area
+    vsp: u16,
+    /// Counts changes of the address window and other moves of
+    /// the frame memory pointer, to reject stale `PausedWriter`s
+    window: u32,
+    /// Tearing Effect output mode, if enabled
+    tearing_effect: Option<command::TearingEffectMode>,
+    /// Current partial/idle mode
+    mode: DisplayMode,
+    controller: Controller,
+    /// Format of pixels written to frame memory
+    pixel_format: PixelFormat,
+    orientation: Orientation,
+    power_state: PowerState,
 }
 
 impl Display {
-    pub fn new<D: DelayMs<u16>>(
+    pub fn new<D: DelayMs<u16> + DelayUs<u16>>(
         sck: spi1::Sck, miso: spi1::Miso, mosi: spi1::Mosi,
         spi: SPI1, spi_dma_stream: spi1::DmaStream, apb2: APB2, clocks: Clocks,
-        tft_dc: TftDc, tft_cs: TftCs,
+        tft_rst: TftRst, tft_dc: TftDc, tft_cs: TftCs,
         ts_pen: TsPen, ts_busy: TsBusy, ts_cs: TsCs,
         sd_cs: SdCs,
+        config: DisplayConfig,
         delay: &mut D,
-    ) -> Result<Self, Error> {
+    ) -> Result<Self, InitError> {
         let mut this = Display {
             spi_state: spi1::State::Reset(spi, sck, miso, mosi),
             spi_dma_stream: Some(spi_dma_stream),
             apb2, clocks,
+            tft_rst,
             tft_dc,
             tft_cs,
             ts_pen,
             ts_busy,
             ts_cs,
             sd_cs,
+            scroll_region: (0, HEIGHT as u16),
+            vsp: 0,
+            window: 0,
+            tearing_effect: None,
+            mode: DisplayMode::NORMAL,
+            controller: Controller::Ili9486,
+            pixel_format: config.pixel_format,
+            orientation: Orientation::PORTRAIT,
+            power_state: PowerState::Reset,
         };
 
         this.set_all_cs_high();
+        this.reset(delay);
+
+        this.controller = match config.controller {
+            Some(controller) => controller,
+            None => match (this.detect_controller(), config.fallback) {
+                (Ok(controller), _) => controller,
+                (Err(InitError::UnknownController { .. }), Some(fallback)) => fallback,
+                (Err(e), _) => return Err(e),
+            },
+        };
+        let pixel_format = this.controller.pixel_format(config.pixel_format);
+        this.pixel_format = pixel_format;
+        let init = config.init.unwrap_or(this.controller.default_init());
+        match init.controller {
+            Some(controller) if controller != this.controller =>
+                return Err(InitError::InitMismatch {
+                    controller: this.controller,
+                    init: controller,
+                }),
+            _ => {}
+        }
 
-        this.tft().write_command(command::SleepOut)?;
-        delay.delay_ms(5);
+        for step in init.steps {
+            this.tft::<&'static [u8]>()
+                .writer(step.command)?
+                .write(step.params)?;
+            if step.delay_ms > 0 {
+                delay.delay_ms(step.delay_ms);
+            }
+        }
 
-        this.tft().write_command(command::DisplayOn)?;
-        this.tft().write_command(command::MemoryAccessControl {
-            rgb_to_bgr: true,
-            row_addr_order: false,
-            col_addr_order: true,
-            row_col_exchange: false,
-            vert_refresh_order: false,
-            horiz_refresh_order: false,
-        })?;
+        let madctl = this.orientation.madctl();
+        this.tft().write_command(madctl)?;
         this.tft().write_command(command::InterfacePixelFormat {
-            cpu_format: command::PixelFormat::Bpp16,
-            rgb_format: command::PixelFormat::Bpp16,
+            cpu_format: pixel_format,
+            rgb_format: pixel_format,
         })?;
 
+        this.sleep_out(delay)?;
+        this.tft().write_command(command::DisplayOn)?;
+        this.power_state = PowerState::On;
+
         Ok(this)
     }
 
+    /// Hardware reset pulse
+    fn reset<D: DelayMs<u16> + DelayUs<u16>>(&mut self, delay: &mut D) {
+        self.tft_rst.set_low();
+        delay.delay_us(10);
+        self.tft_rst.set_high();
+        // Wait for the reset to complete, as required before Sleep Out
+        delay.delay_ms(120);
+        self.power_state = PowerState::Reset;
+    }
+
+    /// Sleep Out, waiting the 120 ms that must pass before a
+    /// following Sleep In
+    fn sleep_out<D: DelayMs<u16>>(&mut self, delay: &mut D) -> Result<(), Error> {
+        self.tft().write_command(command::SleepOut)?;
+        delay.delay_ms(120);
+        self.power_state = PowerState::SleepOut;
+        Ok(())
+    }
+
+    #[allow(unused)]
+    pub fn power_state(&self) -> PowerState {
+        self.power_state
+    }
+
+    /// Turn the display off and enter sleep mode
+    #[allow(unused)]
+    pub fn sleep<D: DelayMs<u16>>(&mut self, delay: &mut D) -> Result<(), Error> {
+        match self.power_state {
+            PowerState::Sleep | PowerState::Reset =>
+                return Ok(()),
+            PowerState::On =>
+                self.tft().write_command(command::DisplayOff)?,
+            PowerState::SleepOut => {}
+        }
+        self.tft().write_command(command::SleepIn)?;
+        // Wait the 120 ms that must pass before a following Sleep Out
+        delay.delay_ms(120);
+        self.power_state = PowerState::Sleep;
+        Ok(())
+    }
+
+    /// Leave sleep mode and turn the display on
+    #[allow(unused)]
+    pub fn wake<D: DelayMs<u16>>(&mut self, delay: &mut D) -> Result<(), Error> {
+        match self.power_state {
+            PowerState::On =>
+                return Ok(()),
+            PowerState::Sleep | PowerState::Reset =>
+                self.sleep_out(delay)?,
+            PowerState::SleepOut => {}
+        }
+        self.tft().write_command(command::DisplayOn)?;
+        self.power_state = PowerState::On;
+        Ok(())
+    }
+
+    /// Probe Read ID4, falling back to Read Display ID
+    /// Identify the controller from its ID registers, regardless of
+    /// the one in use
+    pub fn detect_controller(&mut self) -> Result<Controller, InitError> {
+        let id4 = self.read::<Id4>()?;
+        let display_id = self.read::<DisplayIdentification>()?;
+        Controller::detect(&id4, &display_id)
+            .ok_or(InitError::UnknownController { id4, display_id })
+    }
+
+    pub fn controller(&self) -> Controller {
+        self.controller
+    }
+
+    /// Format expected by `write_pixels()`. May differ from the
+    /// format requested in `new()` if the controller doesn't support
+    /// it.
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    #[allow(unused)]
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Rotate and mirror the picture by reprogramming MADCTL
+    pub fn set_orientation(&mut self, rotation: Rotation, mirror: bool) -> Result<(), Error> {
+        let orientation = Orientation { rotation, mirror };
+        self.tft().write_command(orientation.madctl())?;
+        self.orientation = orientation;
+        self.window = self.window.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Logical width in the current orientation
+    pub fn width(&self) -> usize {
+        self.orientation.size().0
+    }
+
+    /// Logical height in the current orientation
+    pub fn height(&self) -> usize {
+        self.orientation.size().1
+    }
+
+    /// Map raw touch screen readings to logical coordinates
+    pub fn touch_position(&self, calibration: &Calibration, x: u16, y: u16) -> (usize, usize) {
+        let (x, y) = calibration.to_portrait(x, y);
+        self.orientation.to_logical(x, y)
+    }
+
+    /// Load positive and negative gamma curves
+    #[allow(unused)]
+    pub fn set_gamma(&mut self, gamma: &GammaCurve) -> Result<(), Error> {
+        self.tft().write_command(gamma.positive_command())?;
+        self.tft().write_command(gamma.negative_command())
+    }
+
+    /// Define the hardware scrolling area between `top_fixed` and
+    /// `bottom_fixed` lines that stay in place. Lines are counted in
+    /// the native portrait orientation.
+    pub fn set_scroll_region(&mut self, top_fixed: u16, bottom_fixed: u16) -> Result<(), Error> {
+        let tfa = top_fixed.min(HEIGHT as u16);
+        let bfa = bottom_fixed.min(HEIGHT as u16 - tfa);
+        let vsa = HEIGHT as u16 - tfa - bfa;
+        self.tft().write_command(command::VerticalScrollingDefinition {
+            tfa, vsa, bfa,
+        })?;
+        self.scroll_region = (tfa, vsa);
+        Ok(())
+    }
+
+    /// Show the frame memory line `offset` (relative to the
+    /// scrolling area) at the top of the scrolling area
+    pub fn scroll_to(&mut self, offset: u16) -> Result<(), Error> {
+        let (tfa, vsa) = self.scroll_region;
+        let vsp = tfa + if vsa > 0 { offset % vsa } else { 0 };
+        self.tft().write_command(command::VerticalScrollingStartAddress {
+            vsp,
+        })?;
+        self.vsp = vsp;
+        Ok(())
+    }
+
+    /// Frame memory line that the panel shows at `line`, counted in
+    /// the native portrait orientation
+    fn scrolled_line(&self, line: usize) -> usize {
+        let (tfa, vsa) = (self.scroll_region.0 as usize, self.scroll_region.1 as usize);
+        if line < tfa || line >= tfa + vsa {
+            return line;
+        }
+        let offset = (self.vsp as usize).saturating_sub(tfa);
+        tfa + (line - tfa + offset) % vsa
+    }
+
+    /// Logical position in frame memory of the pixel that is shown at
+    /// logical `(x, y)`, which differ within a scrolled area
+    pub fn scrolled_position(&self, x: usize, y: usize) -> (usize, usize) {
+        let (x, y) = self.orientation.to_portrait(x, y);
+        self.orientation.to_logical(x, self.scrolled_line(y))
+    }
+
+    /// Enable or disable the Tearing Effect output
+    #[allow(unused)]
+    pub fn set_tearing_effect(&mut self, mode: Option<command::TearingEffectMode>) -> Result<(), Error> {
+        match mode {
+            Some(mode) =>
+                self.tft().write_command(command::TearingEffectLineOn { mode })?,
+            None =>
+                self.tft().write_command(command::TearingEffectLineOff)?,
+        }
+        self.tearing_effect = mode;
+        Ok(())
+    }
+
+    /// Make the TE output go active when the panel reaches `line`
+    #[allow(unused)]
+    pub fn set_tear_scanline(&mut self, line: u16) -> Result<(), Error> {
+        self.tft().write_command(command::SetTearScanline { sts: line })
+    }
+
+    /// Set backlight brightness through the controller's
+    /// brightness control block
+    #[allow(unused)]
+    pub fn set_brightness(&mut self, brightness: u8) -> Result<(), Error> {
+        self.tft().write_command(command::CtrlDisplay {
+            brightness_control: true,
+            display_dimming: false,
+            backlight: true,
+        })?;
+        self.tft().write_command(command::DisplayBrightness(brightness))
+    }
+
+    /// Let the controller dim the backlight depending on content,
+    /// but not below `min_brightness`
+    #[allow(unused)]
+    pub fn set_cabc_mode(&mut self, mode: command::CabcMode, min_brightness: u8) -> Result<(), Error> {
+        self.tft().write_command(command::CabcMinimumBrightness(min_brightness))?;
+        self.tft().write_command(command::ContentAdaptiveBrightness { mode })
+    }
+
+    #[allow(unused)]
+    pub fn mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    /// Switch partial and idle mode, sending only the commands that
+    /// are required to get from the current mode to `mode`
+    pub fn set_mode(&mut self, mode: DisplayMode) -> Result<(), Error> {
+        if mode.partial != self.mode.partial {
+            match mode.partial {
+                Some((start, end)) => {
+                    self.tft().write_command(command::PartialArea {
+                        sr: start,
+                        er: end,
+                    })?;
+                    if self.mode.partial.is_none() {
+                        self.tft().write_command(command::PartialModeOn)?;
+                    }
+                }
+                None =>
+                    self.tft().write_command(command::NormalDisplayModeOn)?,
+            }
+            self.mode.partial = mode.partial;
+        }
+
+        if mode.idle != self.mode.idle {
+            if mode.idle {
+                self.tft().write_command(command::IdleModeOn)?;
+            } else {
+                self.tft().write_command(command::IdleModeOff)?;
+            }
+            self.mode.idle = mode.idle;
+        }
+
+        Ok(())
+    }
+
+    /// Low-power mode that shows only frame memory lines
+    /// `start..=end` in 8 colours
+    #[allow(unused)]
+    pub fn status_strip(&mut self, start: u16, end: u16) -> Result<(), Error> {
+        self.set_mode(DisplayMode {
+            partial: Some((start, end)),
+            idle: true,
+        })
+    }
+
+    /// Return to full-screen, full-colour display
+    #[allow(unused)]
+    pub fn normal_mode(&mut self) -> Result<(), Error> {
+        self.set_mode(DisplayMode::NORMAL)
+    }
+
     /// Select no SPI slave
     fn set_all_cs_high(&mut self) {
         self.tft_cs.set_high();
@@ -254,7 +727,11 @@ impl Display {
 
     /// Obtain tft interface
     pub fn tft<B: AsRef<[u8]>>(&mut self) -> Tft<DisplaySpi<B>, TftDc, TftCs> {
-        self.setup_spi(spi1::Target::Tft);
+        self.tft_with_target(spi1::Target::Tft)
+    }
+
+    fn tft_with_target<B: AsRef<[u8]>>(&mut self, target: spi1::Target) -> Tft<DisplaySpi<B>, TftDc, TftCs> {
+        self.setup_spi(target);
 
         Tft {
             spi: DisplaySpi {
@@ -267,14 +744,179 @@ impl Display {
         }
     }
 
+    /// Send a read command to tft and decode its response
+    pub fn read<C: ReadCommand>(&mut self) -> Result<C, Error> {
+        self.tft_with_target::<[u8; 0]>(spi1::Target::TftRead)
+            .read_command()
+    }
+
     /// Touch screen input available? Seems to always return true.
     pub fn ts_input(&mut self) -> bool {
         self.ts_pen.is_low()
     }
 
+    /// Set the frame memory window for subsequent pixel writes/reads
+    fn set_window(&mut self, x0: u16, y0: u16, x1: u16, y1: u16) -> Result<(), Error> {
+        self.window = self.window.wrapping_add(1);
+        self.tft().write_command(command::ColumnAddressSet {
+            sc: x0,
+            ec: x1,
+        })?;
+        self.tft().write_command(command::PageAddressSet {
+            sp: y0,
+            ep: y1,
+        })
+    }
+
     /// Send write command to tft and return a DMA writer
     pub fn write_pixels<B: AsRef<[u8]>>(&mut self) -> Result<TftWriter<DisplaySpi<B>, TftCs>, Error> {
-        self.tft::<B>().writer(command::MemoryWrite::number())
+        let (width, height) = self.orientation.size();
+        self.set_window(0, 0, width as u16 - 1, height as u16 - 1)?;
+        self.pixel_writer(command::MemoryWrite::number())
+    }
+
+    /// Continue a pixel stream where `paused` stopped. None if the
+    /// address window has changed since, or frame memory has been
+    /// accessed otherwise.
+    #[allow(unused)]
+    pub fn resume_pixels<B: AsRef<[u8]>>(&mut self, paused: PausedWriter) -> Result<Option<TftWriter<DisplaySpi<B>, TftCs>>, Error> {
+        if paused.window() != Some(self.window) {
+            return Ok(None);
+        }
+        let w = self.pixel_writer(command::MemoryWriteContinue::number())?;
+        Ok(Some(match paused.remaining() {
+            Some(bytes) => w.with_limit(bytes),
+            None => w,
+        }))
+    }
+
+    /// Writer for frame memory, tagged with the current address
+    /// window
+    fn pixel_writer<B: AsRef<[u8]>>(&mut self, reg: u8) -> Result<TftWriter<DisplaySpi<B>, TftCs>, Error> {
+        let window = self.window;
+        self.tft::<B>().writer(reg)
+            .map(|w| w.for_window(window))
+    }
+
+    /// Read back frame memory from the window `(x0, y0)..=(x1, y1)`,
+    /// line by line. Pixels are returned as 3 bytes (R, G, B) with
+    /// 6 significant bits each.
+    pub fn read_pixels(&mut self, x0: u16, y0: u16, x1: u16, y1: u16) -> Result<TftReader<DisplaySpi<[u8; 0]>, TftCs>, Error> {
+        self.set_window(x0, y0, x1, y1)?;
+        self.tft_with_target(spi1::Target::TftRead)
+            .reader(command::MemoryRead::number())
+    }
+
+    /// Resume reading after the last pixel of a previous
+    /// `read_pixels()` reader
+    #[allow(unused)]
+    pub fn read_pixels_continue(&mut self) -> Result<TftReader<DisplaySpi<[u8; 0]>, TftCs>, Error> {
+        self.window = self.window.wrapping_add(1);
+        self.tft_with_target(spi1::Target::TftRead)
+            .reader(command::MemoryReadContinue::number())
+    }
+
+    /// Whole screen in the current orientation
+    pub fn bounds(&self) -> Rect {
+        let (width, height) = self.orientation.size();
+        Rect::new(0, 0, width, height)
+    }
+
+    /// Set the address window to `area`, clipped to the screen, and
+    /// return the clipped area with a writer that expects exactly the
+    /// pixels for it. None if nothing of `area` is visible.
+    pub fn write_area<B: AsRef<[u8]>>(&mut self, area: Rect) -> Result<Option<(Rect, AreaWriter<B>)>, Error> {
+        let area = match area.intersection(&self.bounds()) {
+            Some(area) => area,
+            None => return Ok(None),
+        };
+        self.set_window(area.x as u16, area.y as u16,
+                        (area.right() - 1) as u16, (area.bottom() - 1) as u16)?;
+        let bytes = area.area() * self.pixel_format.bytes_per_pixel();
+        let w = self.pixel_writer(command::MemoryWrite::number())?;
+        Ok(Some((area, w.with_limit(bytes))))
+    }
+
+    /// Fill `area` with one colour. With 16bpp, DMA repeats a single
+    /// pixel so that no buffer needs to be filled.
+    pub fn fill_rect(&mut self, area: Rect, color: (u8, u8, u8)) -> Result<(), Error> {
+        let pixel_format = self.pixel_format;
+        let (r, g, b) = color;
+        let (area, mut w) = match self.write_area::<ScanLine>(area)? {
+            Some(area_writer) => area_writer,
+            None => return Ok(()),
+        };
+        match pixel_format {
+            PixelFormat::Bpp16 =>
+                w.write_repeated_16bpp(rgb_to_16bpp(r, g, b), area.area()),
+            PixelFormat::Bpp18 => {
+                for _ in 0..area.height {
+                    w.write(ScanLine::new(pixel_format, area.width, |_| color))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Copy `src` (or all) of `image` to `(x, y)` on screen. With
+    /// 16bpp, DMA reads straight from the image data.
+    #[allow(unused)]
+    pub fn blit(&mut self, image: &Image, x: usize, y: usize, src: Option<Rect>) -> Result<(), Error> {
+        let src = match src.unwrap_or(image.bounds()).intersection(&image.bounds()) {
+            Some(src) => src,
+            None => return Ok(()),
+        };
+        let area = Rect::new(x, y, src.width, src.height);
+        // Part of the image that remains after clipping to the screen
+        let visible = |dst: Rect| Rect::new(src.x + dst.x - x, src.y + dst.y - y, dst.width, dst.height);
+
+        match self.pixel_format {
+            PixelFormat::Bpp16 => {
+                let (dst, mut w) = match self.write_area::<&'static [u8]>(area)? {
+                    Some(area_writer) => area_writer,
+                    None => return Ok(()),
+                };
+                let src = visible(dst);
+                if src.width == image.width {
+                    // Rows are contiguous, send as many per transfer as
+                    // the DMA counter allows
+                    let rows_per_chunk = (0xFFFF / (2 * src.width)).max(1);
+                    let mut row = src.y;
+                    while row < src.bottom() {
+                        let rows = rows_per_chunk.min(src.bottom() - row);
+                        w.write(image.row(0, row, rows * src.width))?;
+                        row += rows;
+                    }
+                } else {
+                    for row in src.y..src.bottom() {
+                        w.write(image.row(src.x, row, src.width))?;
+                    }
+                }
+            }
+            pixel_format => {
+                let (dst, mut w) = match self.write_area::<ScanLine>(area)? {
+                    Some(area_writer) => area_writer,
+                    None => return Ok(()),
+                };
+                let src = visible(dst);
+                for row in src.y..src.bottom() {
+                    w.write(ScanLine::new(pixel_format, src.width, |col| {
+                        image.get_pixel(src.x + col, row)
+                    }))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `write_pixels()` but starts the frame at the next TE
+    /// event if the Tearing Effect output is enabled
+    #[allow(unused)]
+    pub fn write_pixels_synced<B: AsRef<[u8]>, T: TearingEffect>(&mut self, te: &mut T) -> Result<TftWriter<DisplaySpi<B>, TftCs>, Error> {
+        if self.tearing_effect.is_some() {
+            te.wait();
+        }
+        self.write_pixels()
     }
 
 }
@@ -302,7 +944,7 @@ impl<'a, Buf: AsRef<[u8]>> SpiDmaWrite for DisplaySpi<'a, Buf> {
         // Clear previous
         self.flush()?;
 
-        if buffer.as_ref().len() == 0 {
+        if buffer.as_ref().is_empty() {
             return Ok(());
         }
 
@@ -313,6 +955,16 @@ impl<'a, Buf: AsRef<[u8]>> SpiDmaWrite for DisplaySpi<'a, Buf> {
         Ok(())
     }
 
+    fn write_repeated(&mut self, word: [u8; 2], count: usize) -> Result<(), Self::Error> {
+        self.flush()?;
+
+        // Hold the stream while its registers are used directly
+        let stream = self.spi_dma_stream.take().unwrap();
+        dma_repeat::write_repeated(word, count);
+        *self.spi_dma_stream = Some(stream);
+        Ok(())
+    }
+
     fn flush(&mut self) -> Result<(), Self::Error> {
         match self.dma_xfer.take() {
             Some(xfer) => {
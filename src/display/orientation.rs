@@ -0,0 +1,139 @@
+use super::{WIDTH, HEIGHT};
+use super::ili9486::command::MemoryAccessControl;
+
+/// Clockwise rotation of the picture relative to the native portrait
+/// orientation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(unused)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    /// Flip horizontally after rotating
+    pub mirror: bool,
+}
+
+impl Orientation {
+    pub const PORTRAIT: Orientation = Orientation {
+        rotation: Rotation::Deg0,
+        mirror: false,
+    };
+
+    /// Logical (width, height)
+    pub fn size(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (WIDTH, HEIGHT),
+            Rotation::Deg90 | Rotation::Deg270 => (HEIGHT, WIDTH),
+        }
+    }
+
+    pub fn madctl(&self) -> MemoryAccessControl {
+        let (mut mx, mut my, mv) = match self.rotation {
+            Rotation::Deg0 => (true, false, false),
+            Rotation::Deg90 => (false, false, true),
+            Rotation::Deg180 => (false, true, false),
+            Rotation::Deg270 => (true, true, true),
+        };
+        if self.mirror {
+            // Logical X runs along rows when exchanged
+            if mv {
+                my = !my;
+            } else {
+                mx = !mx;
+            }
+        }
+
+        MemoryAccessControl {
+            rgb_to_bgr: true,
+            row_addr_order: my,
+            col_addr_order: mx,
+            row_col_exchange: mv,
+            vert_refresh_order: false,
+            horiz_refresh_order: false,
+        }
+    }
+
+    /// Map coordinates in the native portrait orientation to logical
+    /// coordinates
+    pub fn to_logical(self, x: usize, y: usize) -> (usize, usize) {
+        let (x, y) = match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (y, WIDTH - 1 - x),
+            Rotation::Deg180 => (WIDTH - 1 - x, HEIGHT - 1 - y),
+            Rotation::Deg270 => (HEIGHT - 1 - y, x),
+        };
+        if self.mirror {
+            let (width, _) = self.size();
+            (width - 1 - x, y)
+        } else {
+            (x, y)
+        }
+    }
+
+    /// Map logical coordinates back to the native portrait
+    /// orientation
+    pub fn to_portrait(self, x: usize, y: usize) -> (usize, usize) {
+        let x = if self.mirror {
+            let (width, _) = self.size();
+            width - 1 - x
+        } else {
+            x
+        };
+        match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (WIDTH - 1 - y, x),
+            Rotation::Deg180 => (WIDTH - 1 - x, HEIGHT - 1 - y),
+            Rotation::Deg270 => (y, HEIGHT - 1 - x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_portrait_inverts_to_logical() {
+        for &rotation in &[Rotation::Deg0, Rotation::Deg90, Rotation::Deg180, Rotation::Deg270] {
+            for &mirror in &[false, true] {
+                let orientation = Orientation { rotation, mirror };
+                for &(x, y) in &[(0, 0), (1, 2), (WIDTH - 1, 7), (5, HEIGHT - 1)] {
+                    let (lx, ly) = orientation.to_logical(x, y);
+                    assert_eq!(orientation.to_portrait(lx, ly), (x, y));
+                }
+            }
+        }
+    }
+
+    /// Frame memory position that `madctl` maps the logical `(x, y)`
+    /// to, in the coordinates of `Orientation::PORTRAIT`, which has
+    /// the column order reversed
+    fn madctl_to_portrait(madctl: &MemoryAccessControl, x: usize, y: usize) -> (usize, usize) {
+        let (col, row) = if madctl.row_col_exchange { (y, x) } else { (x, y) };
+        let col = if madctl.col_addr_order { WIDTH - 1 - col } else { col };
+        let row = if madctl.row_addr_order { HEIGHT - 1 - row } else { row };
+        (WIDTH - 1 - col, row)
+    }
+
+    #[test]
+    fn madctl_matches_coordinate_mapping() {
+        for &rotation in &[Rotation::Deg0, Rotation::Deg90, Rotation::Deg180, Rotation::Deg270] {
+            for &mirror in &[false, true] {
+                let orientation = Orientation { rotation, mirror };
+                let madctl = orientation.madctl();
+                let (width, height) = orientation.size();
+                for &(x, y) in &[(0, 0), (1, 2), (width - 1, 7), (5, height - 1), (width - 1, height - 1)] {
+                    let portrait = madctl_to_portrait(&madctl, x, y);
+                    assert_eq!(orientation.to_portrait(x, y), portrait, "{:?} at {:?}", orientation, (x, y));
+                    assert_eq!(orientation.to_logical(portrait.0, portrait.1), (x, y));
+                }
+            }
+        }
+    }
+}
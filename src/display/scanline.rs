@@ -1,23 +1,25 @@
-use super::{WIDTH, rgb_to_16bpp};
+use super::{MAX_WIDTH, write_rgb};
+use super::ili9486::command::PixelFormat;
 
-/// A 320px scan line for 16-bit data
+/// A scan line of up to `MAX_WIDTH` pixels for 16- or 18-bit data
 pub struct ScanLine {
-    buf: [u8; 2 * WIDTH],
+    buf: [u8; 3 * MAX_WIDTH],
+    len: usize,
 }
 
 impl ScanLine {
     /// Initialize from user-defined callback
     #[inline(always)]
-    pub fn new<F: Fn(usize) -> (u8, u8, u8)>(f: F) -> Self {
+    pub fn new<F: FnMut(usize) -> (u8, u8, u8)>(format: PixelFormat, width: usize, mut f: F) -> Self {
         let mut this = ScanLine {
-            buf: unsafe { core::mem::uninitialized() }}
-        ;
+            buf: unsafe { core::mem::uninitialized() },
+            len: format.bytes_per_pixel() * width.min(MAX_WIDTH),
+        };
         let mut i = 0;
         let mut x = 0;
-        while i < this.buf.len() {
+        while i < this.len {
             let (r, g, b) = f(x);
-            this.buf[i..(i + 2)].copy_from_slice(&rgb_to_16bpp(r, g, b));
-            i += 2;
+            i += write_rgb(format, &mut this.buf[i..], r, g, b);
             x += 1;
         }
         this
@@ -26,6 +28,6 @@ impl ScanLine {
 
 impl AsRef<[u8]> for ScanLine {
     fn as_ref(&self) -> &[u8] {
-        &self.buf[..]
+        &self.buf[..self.len]
     }
 }
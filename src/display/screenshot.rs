@@ -0,0 +1,122 @@
+//! Capture frame memory into a PPM file on the debugging host
+
+use core::fmt;
+use sh::{syscall, nr};
+use stm32f429_hal::spi::Error as SpiError;
+
+use super::{Display, MAX_WIDTH};
+
+#[derive(Debug)]
+pub enum Error {
+    Spi(SpiError),
+    /// Semihosting file operation failed
+    Host,
+}
+
+impl From<SpiError> for Error {
+    fn from(e: SpiError) -> Self {
+        Error::Spi(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Spi(ref e) =>
+                write!(f, "SPI error: {:?}", e),
+            Error::Host =>
+                write!(f, "Host file operation failed"),
+        }
+    }
+}
+
+/// Maximum path length including the terminating nul
+const MAX_PATH: usize = 128;
+
+/// File opened through semihosting
+struct HostFile {
+    fd: usize,
+}
+
+impl HostFile {
+    fn create(path: &str) -> Result<Self, Error> {
+        let mut name = [0u8; MAX_PATH];
+        if path.len() >= name.len() {
+            return Err(Error::Host);
+        }
+        name[..path.len()].copy_from_slice(path.as_bytes());
+
+        let fd = unsafe {
+            syscall(nr::OPEN, &[name.as_ptr() as usize, nr::open::W_TRUNC_BINARY, path.len()])
+        };
+        if fd as isize == -1 {
+            return Err(Error::Host);
+        }
+        Ok(HostFile { fd })
+    }
+
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Error> {
+        while !buf.is_empty() {
+            // Returns the number of bytes *not* written
+            let rest = unsafe {
+                syscall(nr::WRITE, &[self.fd, buf.as_ptr() as usize, buf.len()])
+            };
+            if rest >= buf.len() {
+                return Err(Error::Host);
+            }
+            let written = buf.len() - rest;
+            buf = &buf[written..];
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Write for HostFile {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes())
+            .map_err(|_| fmt::Error)
+    }
+}
+
+impl Drop for HostFile {
+    fn drop(&mut self) {
+        unsafe {
+            syscall(nr::CLOSE, &[self.fd]);
+        }
+    }
+}
+
+/// Read what the screen shows and write it to `path` on the host as
+/// a binary PPM (P6) image. Rows of a scrolled area are read from
+/// where `Display::scroll_to()` has placed them in frame memory.
+#[allow(unused)]
+pub fn screenshot(display: &mut Display, path: &str) -> Result<(), Error> {
+    let width = display.width();
+    let height = display.height();
+    let mut file = HostFile::create(path)?;
+    fmt::Write::write_fmt(&mut file, format_args!("P6\n{} {}\n255\n", width, height))
+        .map_err(|_| Error::Host)?;
+
+    let mut frame_buf = [0u8; 3 * MAX_WIDTH];
+    let mut buf = [0u8; 3 * MAX_WIDTH];
+    let frame_line = &mut frame_buf[..3 * width];
+    let line = &mut buf[..3 * width];
+    for y in 0..height {
+        // Scrolling moves either whole rows or, when rotated by 90°,
+        // single pixels within the row
+        let (_, frame_y) = display.scrolled_position(0, y);
+        display.read_pixels(0, frame_y as u16, width as u16 - 1, frame_y as u16)?
+            .read(frame_line)?;
+        for x in 0..width {
+            let (frame_x, _) = display.scrolled_position(x, y);
+            for i in 0..3 {
+                let b = frame_line[3 * frame_x + i];
+                // Expand 6 significant bits to 8
+                line[3 * x + i] = (b & 0xFC) | (b >> 6);
+            }
+        }
+        file.write_all(line)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,643 @@
+//! Geometric primitives rasterised one row at a time, so that they
+//! can be evaluated from a scanline callback without a framebuffer.
+//!
+//! Coordinates are signed to allow shapes that extend beyond the
+//! top or left edge. Pixel `(x, y)` is sampled at its centre.
+
+use super::Rect;
+
+/// Spans that a single row can hold, excess is dropped
+const MAX_SPANS: usize = 16;
+
+/// Covered horizontal ranges `[start, end)` of one row
+pub struct Spans {
+    spans: [(usize, usize); MAX_SPANS],
+    len: usize,
+}
+
+impl Spans {
+    pub fn new() -> Self {
+        Spans {
+            spans: [(0, 0); MAX_SPANS],
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Clips to the left edge and ignores empty spans
+    pub fn push(&mut self, start: i32, end: i32) {
+        let start = start.max(0);
+        if end > start && self.len < MAX_SPANS {
+            self.spans[self.len] = (start as usize, end as usize);
+            self.len += 1;
+        }
+    }
+
+    pub fn as_slice(&self) -> &[(usize, usize)] {
+        &self.spans[..self.len]
+    }
+
+    pub fn contains(&self, x: usize) -> bool {
+        self.as_slice().iter()
+            .any(|&(start, end)| x >= start && x < end)
+    }
+}
+
+/// Something that can be rasterised per row
+pub trait Shape {
+    /// Area that may be covered, clipped to the top left
+    fn bounds(&self) -> Rect;
+    /// Add the covered spans of row `y`
+    fn spans(&self, y: usize, spans: &mut Spans);
+
+    /// Test a single pixel. To test a whole row, fetch its
+    /// `spans()` once instead.
+    #[allow(unused)]
+    fn contains(&self, x: usize, y: usize) -> bool {
+        let mut spans = Spans::new();
+        self.spans(y, &mut spans);
+        spans.contains(x)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)]
+pub enum Style {
+    Fill,
+    /// Outline of the given width, drawn inside the shape
+    Stroke(i32),
+}
+
+fn bounds(x0: i32, y0: i32, x1: i32, y1: i32) -> Rect {
+    let (x0, y0) = (x0.max(0), y0.max(0));
+    let (x1, y1) = (x1.max(x0), y1.max(y0));
+    Rect::new(x0 as usize, y0 as usize, (x1 - x0) as usize, (y1 - y0) as usize)
+}
+
+/// Division rounding towards negative infinity, `d > 0`
+fn div_floor(n: i64, d: i64) -> i64 {
+    if n >= 0 {
+        n / d
+    } else {
+        -((d - 1 - n) / d)
+    }
+}
+
+fn isqrt(n: i64) -> i64 {
+    if n <= 0 {
+        return 0;
+    }
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// Span of row `y` within the ellipse inscribed in the box at
+/// `(x, top)` of `width` x `height`
+fn ellipse_row(x: i32, top: i32, width: i32, height: i32, y: i32) -> Option<(i32, i32)> {
+    if width <= 0 || height <= 0 || y < top || y >= top + height {
+        return None;
+    }
+    // In half pixels, the centre lies at `2 * x + width` and pixel
+    // `px` at `2 * px + 1`
+    let (w, h) = (width as i64, height as i64);
+    let dy = (2 * (y - top) + 1) as i64 - h;
+    let max_dx = isqrt(w * w * (h * h - dy * dy) / (h * h));
+    let c = (2 * x) as i64 + w - 1;
+    let start = -div_floor(max_dx - c, 2);
+    let end = div_floor(c + max_dx, 2) + 1;
+    if start < end {
+        Some((start as i32, end as i32))
+    } else {
+        None
+    }
+}
+
+/// Push the outer span of a convex shape, minus the inner span
+/// when stroking
+fn push_convex(outer: Option<(i32, i32)>, inner: Option<(i32, i32)>, style: Style, spans: &mut Spans) {
+    match (outer, inner, style) {
+        (None, _, _) => {}
+        (Some((x0, x1)), Some((inner_x0, inner_x1)), Style::Stroke(_)) => {
+            spans.push(x0, inner_x0);
+            spans.push(inner_x1, x1);
+        }
+        (Some((x0, x1)), _, _) =>
+            spans.push(x0, x1),
+    }
+}
+
+/// One pixel wide line, including both end points
+#[derive(Debug, Clone, Copy)]
+pub struct Line {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+}
+
+impl Line {
+    pub fn new(x0: i32, y0: i32, x1: i32, y1: i32) -> Self {
+        Line { x0, y0, x1, y1 }
+    }
+}
+
+impl Shape for Line {
+    fn bounds(&self) -> Rect {
+        bounds(self.x0.min(self.x1), self.y0.min(self.y1),
+               self.x0.max(self.x1) + 1, self.y0.max(self.y1) + 1)
+    }
+
+    fn spans(&self, y: usize, spans: &mut Spans) {
+        let y = y as i32;
+        // Top to bottom
+        let (x0, y0, x1, y1) = if self.y0 <= self.y1 {
+            (self.x0, self.y0, self.x1, self.y1)
+        } else {
+            (self.x1, self.y1, self.x0, self.y0)
+        };
+        if y < y0 || y > y1 {
+            return;
+        }
+        if y0 == y1 {
+            spans.push(x0.min(x1), x0.max(x1) + 1);
+            return;
+        }
+
+        // Cover the line from half a row above to half a row
+        // below, in units of half rows
+        let (dx, dy) = ((x1 - x0) as i64, (y1 - y0) as i64);
+        let x_at = |t: i32| {
+            let n = (t - 2 * y0) as i64 * dx;
+            let d = 2 * dy;
+            // Round to nearest
+            x0 + div_floor(2 * n + d, 2 * d) as i32
+        };
+        let xa = x_at((2 * y - 1).max(2 * y0));
+        let xb = x_at((2 * y + 1).min(2 * y1));
+        spans.push(xa.min(xb), xa.max(xb) + 1);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rectangle {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub style: Style,
+}
+
+impl Rectangle {
+    #[allow(unused)]
+    pub fn new(x: i32, y: i32, width: i32, height: i32, style: Style) -> Self {
+        Rectangle { x, y, width, height, style }
+    }
+
+    fn row(&self, y: i32, inset: i32) -> Option<(i32, i32)> {
+        if y >= self.y + inset && y < self.y + self.height - inset &&
+            2 * inset < self.width
+        {
+            Some((self.x + inset, self.x + self.width - inset))
+        } else {
+            None
+        }
+    }
+}
+
+impl Shape for Rectangle {
+    fn bounds(&self) -> Rect {
+        bounds(self.x, self.y, self.x + self.width, self.y + self.height)
+    }
+
+    fn spans(&self, y: usize, spans: &mut Spans) {
+        let y = y as i32;
+        let inner = match self.style {
+            Style::Stroke(width) => self.row(y, width),
+            Style::Fill => None,
+        };
+        push_convex(self.row(y, 0), inner, self.style, spans);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RoundedRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub radius: i32,
+    pub style: Style,
+}
+
+impl RoundedRect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32, radius: i32, style: Style) -> Self {
+        RoundedRect { x, y, width, height, radius, style }
+    }
+
+    fn row(&self, y: i32, inset: i32) -> Option<(i32, i32)> {
+        let (left, top) = (self.x + inset, self.y + inset);
+        let (right, bottom) = (self.x + self.width - inset, self.y + self.height - inset);
+        if y < top || y >= bottom || left >= right {
+            return None;
+        }
+
+        let radius = (self.radius - inset)
+            .min((right - left - 1) / 2)
+            .min((bottom - top - 1) / 2)
+            .max(0);
+        // Corners are quarters of a circle with odd diameter
+        let diameter = 2 * radius + 1;
+        let corner_top = if y < top + radius {
+            top
+        } else if y >= bottom - radius {
+            bottom - diameter
+        } else {
+            return Some((left, right));
+        };
+        let (start, _) = ellipse_row(left, corner_top, diameter, diameter, y)?;
+        Some((start, right - (start - left)))
+    }
+}
+
+impl Shape for RoundedRect {
+    fn bounds(&self) -> Rect {
+        bounds(self.x, self.y, self.x + self.width, self.y + self.height)
+    }
+
+    fn spans(&self, y: usize, spans: &mut Spans) {
+        let y = y as i32;
+        let inner = match self.style {
+            Style::Stroke(width) => self.row(y, width),
+            Style::Fill => None,
+        };
+        push_convex(self.row(y, 0), inner, self.style, spans);
+    }
+}
+
+/// Ellipse inscribed in a box of any size
+#[derive(Debug, Clone, Copy)]
+pub struct Ellipse {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub style: Style,
+}
+
+impl Ellipse {
+    #[allow(unused)]
+    pub fn new(x: i32, y: i32, width: i32, height: i32, style: Style) -> Self {
+        Ellipse { x, y, width, height, style }
+    }
+
+    /// Centred on pixel `(cx, cy)`, with odd width and height
+    #[allow(unused)]
+    pub fn centered(cx: i32, cy: i32, rx: i32, ry: i32, style: Style) -> Self {
+        Ellipse::new(cx - rx, cy - ry, 2 * rx + 1, 2 * ry + 1, style)
+    }
+
+    fn row(&self, y: i32, inset: i32) -> Option<(i32, i32)> {
+        ellipse_row(self.x + inset, self.y + inset,
+                    self.width - 2 * inset, self.height - 2 * inset, y)
+    }
+}
+
+impl Shape for Ellipse {
+    fn bounds(&self) -> Rect {
+        bounds(self.x, self.y, self.x + self.width, self.y + self.height)
+    }
+
+    fn spans(&self, y: usize, spans: &mut Spans) {
+        let y = y as i32;
+        let inner = match self.style {
+            Style::Stroke(width) => self.row(y, width),
+            Style::Fill => None,
+        };
+        push_convex(self.row(y, 0), inner, self.style, spans);
+    }
+}
+
+/// An `Ellipse` with equal width and height
+#[derive(Debug, Clone, Copy)]
+pub struct Circle(pub Ellipse);
+
+impl Circle {
+    /// Centred on pixel `(cx, cy)`
+    #[allow(unused)]
+    pub fn new(cx: i32, cy: i32, radius: i32, style: Style) -> Self {
+        Circle(Ellipse::centered(cx, cy, radius, radius, style))
+    }
+
+    #[allow(unused)]
+    pub fn with_diameter(x: i32, y: i32, diameter: i32, style: Style) -> Self {
+        Circle(Ellipse::new(x, y, diameter, diameter, style))
+    }
+}
+
+impl Shape for Circle {
+    fn bounds(&self) -> Rect {
+        self.0.bounds()
+    }
+
+    fn spans(&self, y: usize, spans: &mut Spans) {
+        self.0.spans(y, spans)
+    }
+}
+
+/// `sin()` of whole degrees 0 to 90, scaled by `1 << 14`
+const SINE: [i32; 91] = [
+    0, 286, 572, 857, 1143, 1428, 1713, 1997, 2280, 2563,
+    2845, 3126, 3406, 3686, 3964, 4240, 4516, 4790, 5063, 5334,
+    5604, 5872, 6138, 6402, 6664, 6924, 7182, 7438, 7692, 7943,
+    8192, 8438, 8682, 8923, 9162, 9397, 9630, 9860, 10087, 10311,
+    10531, 10749, 10963, 11174, 11381, 11585, 11786, 11982, 12176, 12365,
+    12551, 12733, 12911, 13085, 13255, 13421, 13583, 13741, 13894, 14044,
+    14189, 14330, 14466, 14598, 14726, 14849, 14968, 15082, 15191, 15296,
+    15396, 15491, 15582, 15668, 15749, 15826, 15897, 15964, 16026, 16083,
+    16135, 16182, 16225, 16262, 16294, 16322, 16344, 16362, 16374, 16382,
+    16384,
+];
+
+fn sin(degrees: i32) -> i32 {
+    let degrees = degrees.rem_euclid(360);
+    match degrees {
+        0..=90 => SINE[degrees as usize],
+        91..=180 => SINE[(180 - degrees) as usize],
+        181..=270 => -SINE[(degrees - 180) as usize],
+        _ => -SINE[(360 - degrees) as usize],
+    }
+}
+
+fn cos(degrees: i32) -> i32 {
+    sin(degrees + 90)
+}
+
+/// Section of a circle outline. Angles are in degrees, clockwise
+/// on screen from the positive x axis.
+#[derive(Debug, Clone, Copy)]
+pub struct Arc {
+    pub cx: i32,
+    pub cy: i32,
+    pub radius: i32,
+    pub start: i32,
+    pub sweep: i32,
+    pub width: i32,
+}
+
+impl Arc {
+    #[allow(unused)]
+    pub fn new(cx: i32, cy: i32, radius: i32, start: i32, sweep: i32, width: i32) -> Self {
+        Arc { cx, cy, radius, start, sweep, width }
+    }
+
+    /// Whether the direction `(dx, dy)` lies within the sweep
+    fn in_sweep(&self, dx: i32, dy: i32) -> bool {
+        if self.sweep >= 360 {
+            return true;
+        }
+        let end = self.start + self.sweep;
+        // Cross products of the start and end directions with
+        // `(dx, dy)`: positive when clockwise of it
+        let after_start = cos(self.start) as i64 * dy as i64 - sin(self.start) as i64 * dx as i64;
+        let before_end = dx as i64 * sin(end) as i64 - dy as i64 * cos(end) as i64;
+        if self.sweep <= 180 {
+            after_start >= 0 && before_end >= 0
+        } else {
+            after_start >= 0 || before_end >= 0
+        }
+    }
+}
+
+impl Shape for Arc {
+    fn bounds(&self) -> Rect {
+        Circle::new(self.cx, self.cy, self.radius, Style::Fill).bounds()
+    }
+
+    fn spans(&self, y: usize, spans: &mut Spans) {
+        let mut ring = Spans::new();
+        Circle::new(self.cx, self.cy, self.radius, Style::Stroke(self.width))
+            .spans(y, &mut ring);
+
+        // Split the ring into runs of pixels within the sweep
+        let dy = y as i32 - self.cy;
+        for &(start, end) in ring.as_slice() {
+            let mut run_start = None;
+            for x in start..end {
+                let inside = self.in_sweep(x as i32 - self.cx, dy);
+                match (run_start, inside) {
+                    (None, true) =>
+                        run_start = Some(x),
+                    (Some(x0), false) => {
+                        spans.push(x0 as i32, x as i32);
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(x0) = run_start {
+                spans.push(x0 as i32, end as i32);
+            }
+        }
+    }
+}
+
+/// Vertices that a `Polygon` can have, so that its crossings with
+/// a row always fit into `Spans`
+pub const MAX_VERTICES: usize = 2 * MAX_SPANS;
+
+/// Filled polygon using the even-odd rule. Vertices lie on pixel
+/// corners, so like `Rect` it covers pixels whose centres lie
+/// inside.
+#[derive(Debug, Clone, Copy)]
+pub struct Polygon<'a> {
+    points: &'a [(i32, i32)],
+}
+
+impl<'a> Polygon<'a> {
+    /// `None` with more than `MAX_VERTICES` points
+    #[allow(unused)]
+    pub fn new(points: &'a [(i32, i32)]) -> Option<Self> {
+        if points.len() <= MAX_VERTICES {
+            Some(Polygon { points })
+        } else {
+            None
+        }
+    }
+
+    #[allow(unused)]
+    pub fn points(&self) -> &'a [(i32, i32)] {
+        self.points
+    }
+}
+
+impl<'a> Shape for Polygon<'a> {
+    fn bounds(&self) -> Rect {
+        let mut x0 = i32::max_value();
+        let mut y0 = i32::max_value();
+        let mut x1 = i32::min_value();
+        let mut y1 = i32::min_value();
+        for &(x, y) in self.points {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        }
+        bounds(x0, y0, x1, y1)
+    }
+
+    fn spans(&self, y: usize, spans: &mut Spans) {
+        let y = y as i32;
+        let mut crossings = [0i32; MAX_VERTICES];
+        let mut len = 0;
+        for i in 0..self.points.len() {
+            let (x0, y0) = self.points[i];
+            let (x1, y1) = self.points[(i + 1) % self.points.len()];
+            // Edge crosses the row centre `y + 1/2`
+            let crosses = (y0 <= y && y < y1) || (y1 <= y && y < y0);
+            if crosses {
+                // Crossing at `n / d`, with `d > 0`
+                let dy = (y1 - y0) as i64;
+                let n = (2 * x0) as i64 * dy + (2 * (y - y0) + 1) as i64 * (x1 - x0) as i64;
+                let (n, d) = (n * dy.signum(), 2 * dy.abs());
+                // First pixel whose centre is not left of it
+                crossings[len] = -div_floor(d - 2 * n, 2 * d) as i32;
+                len += 1;
+            }
+        }
+
+        let crossings = &mut crossings[..len];
+        crossings.sort_unstable();
+        for pair in crossings.chunks(2) {
+            if pair.len() == 2 {
+                spans.push(pair[0], pair[1]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spans of every row within the bounds
+    fn rows<S: Shape>(shape: &S) -> Vec<Vec<(usize, usize)>> {
+        let bounds = shape.bounds();
+        (bounds.y..bounds.y + bounds.height)
+            .map(|y| {
+                let mut spans = Spans::new();
+                shape.spans(y, &mut spans);
+                spans.as_slice().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shallow_line() {
+        assert_eq!(rows(&Line::new(0, 0, 6, 2)), [
+            vec![(0, 3)],
+            vec![(2, 6)],
+            vec![(5, 7)],
+        ]);
+    }
+
+    #[test]
+    fn steep_line() {
+        assert_eq!(rows(&Line::new(2, 0, 0, 4)), [
+            vec![(2, 3)],
+            vec![(1, 3)],
+            vec![(1, 2)],
+            vec![(0, 2)],
+            vec![(0, 1)],
+        ]);
+    }
+
+    #[test]
+    fn odd_circle() {
+        assert_eq!(rows(&Circle::new(2, 2, 2, Style::Fill)), [
+            vec![(1, 4)],
+            vec![(0, 5)],
+            vec![(0, 5)],
+            vec![(0, 5)],
+            vec![(1, 4)],
+        ]);
+    }
+
+    #[test]
+    fn even_circle() {
+        assert_eq!(rows(&Circle::with_diameter(0, 0, 4, Style::Fill)), [
+            vec![(1, 3)],
+            vec![(0, 4)],
+            vec![(0, 4)],
+            vec![(1, 3)],
+        ]);
+        assert_eq!(rows(&Circle::with_diameter(0, 0, 4, Style::Stroke(1))), [
+            vec![(1, 3)],
+            vec![(0, 1), (3, 4)],
+            vec![(0, 1), (3, 4)],
+            vec![(1, 3)],
+        ]);
+    }
+
+    #[test]
+    fn even_ellipse() {
+        assert_eq!(rows(&Ellipse::new(0, 0, 6, 3, Style::Fill)), [
+            vec![(1, 5)],
+            vec![(0, 6)],
+            vec![(1, 5)],
+        ]);
+    }
+
+    #[test]
+    fn rounded_rect() {
+        assert_eq!(rows(&RoundedRect::new(0, 0, 8, 6, 2, Style::Fill)), [
+            vec![(1, 7)],
+            vec![(0, 8)],
+            vec![(0, 8)],
+            vec![(0, 8)],
+            vec![(0, 8)],
+            vec![(1, 7)],
+        ]);
+    }
+
+    #[test]
+    fn arc_across_zero() {
+        // Right quarter of the ring, from 315° through 0° to 45°
+        assert_eq!(rows(&Arc::new(3, 3, 3, 315, 90, 1)), [
+            vec![],
+            vec![(5, 6)],
+            vec![(6, 7)],
+            vec![(6, 7)],
+            vec![(6, 7)],
+            vec![(5, 6)],
+            vec![],
+        ]);
+    }
+
+    #[test]
+    fn concave_polygon() {
+        let points = [(0, 0), (4, 2), (8, 0), (8, 4), (0, 4)];
+        let polygon = Polygon::new(&points).unwrap();
+        // Sampled at the row centres `y + 1/2`
+        assert_eq!(rows(&polygon), [
+            vec![(0, 1), (7, 8)],
+            vec![(0, 3), (5, 8)],
+            vec![(0, 8)],
+            vec![(0, 8)],
+        ]);
+    }
+
+    #[test]
+    fn polygon_vertex_limit() {
+        let points = [(0, 0); MAX_VERTICES + 1];
+        assert!(Polygon::new(&points[..MAX_VERTICES]).is_some());
+        assert!(Polygon::new(&points).is_none());
+    }
+}
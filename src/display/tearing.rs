@@ -0,0 +1,41 @@
+//! Synchronizing frame updates with the controller's Tearing Effect
+//! output. Its TE pin must be wired to a GPIO input.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use embedded_hal::digital::InputPin;
+
+/// Blocks until the next TE event
+pub trait TearingEffect {
+    fn wait(&mut self);
+}
+
+/// Polls the TE input for a rising edge
+#[allow(unused)]
+pub struct TePin<P: InputPin>(pub P);
+
+impl<P: InputPin> TearingEffect for TePin<P> {
+    fn wait(&mut self) {
+        while self.0.is_high() {}
+        while self.0.is_low() {}
+    }
+}
+
+/// Flag that is raised by an EXTI interrupt handler on the TE edge
+#[allow(unused)]
+pub struct TeFlag(pub &'static AtomicBool);
+
+impl TeFlag {
+    /// Call from the interrupt handler
+    #[allow(unused)]
+    pub fn signal(flag: &AtomicBool) {
+        flag.store(true, Ordering::Release);
+    }
+}
+
+impl TearingEffect for TeFlag {
+    fn wait(&mut self) {
+        // Discard an edge that happened before we started waiting
+        self.0.store(false, Ordering::Relaxed);
+        while !self.0.swap(false, Ordering::Acquire) {}
+    }
+}
@@ -3,6 +3,7 @@
 use embedded_hal::digital::{InputPin, OutputPin};
 
 use super::super::spi::SpiDmaWrite;
+use super::{WIDTH, HEIGHT};
 
 mod command;
 use self::command::Command;
@@ -24,6 +25,38 @@ pub mod channels {
 
 const X_PLATE_OHMS: u32 = 400;
 
+/// Raw readings at the edges of the panel
+#[derive(Debug, Clone, Copy)]
+pub struct Calibration {
+    x_min: u16,
+    y_min: u16,
+    x_max: u16,
+    y_max: u16,
+}
+
+impl Calibration {
+    /// `None` unless each minimum lies below its maximum
+    pub fn new(x_min: u16, y_min: u16, x_max: u16, y_max: u16) -> Option<Self> {
+        if x_min < x_max && y_min < y_max {
+            Some(Calibration { x_min, y_min, x_max, y_max })
+        } else {
+            None
+        }
+    }
+
+    /// Map raw readings to pixels in the native portrait orientation
+    pub fn to_portrait(self, x: u16, y: u16) -> (usize, usize) {
+        let x = x.max(self.x_min).min(self.x_max) - self.x_min;
+        let y = y.max(self.y_min).min(self.y_max) - self.y_min;
+        let x_range = (self.x_max - self.x_min) as usize;
+        let y_range = (self.y_max - self.y_min) as usize;
+        (
+            ((WIDTH - 1) * x as usize) / x_range,
+            ((HEIGHT - 1) * (y_range - y as usize)) / y_range,
+        )
+    }
+}
+
 pub struct Ts<'a, SPI: SpiDmaWrite, CS: OutputPin, Busy: InputPin> {
     pub spi: SPI,
     pub cs: &'a mut CS,
@@ -84,7 +117,7 @@ impl<'a, SPI: SpiDmaWrite, CS: OutputPin, Busy: InputPin> Ts<'a, SPI, CS, Busy>
 }
 
 fn nearest_avg(xs: &[u16]) -> u16 {
-    if xs.len() == 0 {
+    if xs.is_empty() {
         return xs[0];
     }
     fn diff(x1: u16, x2: u16) -> u16 {
@@ -172,3 +205,21 @@ impl<'a, SPI: SpiDmaWrite, CS: OutputPin, Busy: InputPin, I: Iterator<Item=Comma
 fn read_12bits(buf: &[u8]) -> u16 {
     ((buf[1] as u16) << 5) | ((buf[0] as u16) >> 3)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibration() {
+        assert!(Calibration::new(100, 100, 100, 200).is_none());
+        assert!(Calibration::new(100, 200, 300, 100).is_none());
+
+        let calibration = Calibration::new(100, 200, 300, 400).unwrap();
+        assert_eq!(calibration.to_portrait(100, 400), (0, 0));
+        assert_eq!(calibration.to_portrait(300, 200), (WIDTH - 1, HEIGHT - 1));
+        // Clamped to the edges
+        assert_eq!(calibration.to_portrait(0, 0), (0, HEIGHT - 1));
+        assert_eq!(calibration.to_portrait(0xFFFF, 0xFFFF), (WIDTH - 1, 0));
+    }
+}
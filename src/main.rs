@@ -1,7 +1,10 @@
-#![no_std]
-#![no_main]
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
 
+#[cfg(not(test))]
 extern crate panic_semihosting;
+#[cfg(test)]
+extern crate core;
 extern crate cortex_m;
 #[macro_use]
 extern crate cortex_m_rt as rt;
@@ -10,6 +13,7 @@ extern crate cortex_m_semihosting as sh;
 extern crate embedded_hal;
 extern crate nb;
 extern crate vga_framebuffer;
+extern crate embedded_graphics;
 
 use core::fmt::Write;
 use stm32f429_hal::{
@@ -21,17 +25,31 @@ use stm32f429_hal::{
     time::U32Ext,
     dma::DmaExt,
 };
-use embedded_hal::{
-    digital::OutputPin,
-    blocking::delay::DelayUs,
-};
+use embedded_hal::digital::OutputPin;
 
 mod spi;
 mod display;
-use display::{Display, WIDTH, HEIGHT, console::Console, ScanLine};
+use display::{Display, DisplayConfig, Controller, console::Console, dirty::DirtyRegion};
+use display::ili9486::command::{
+    PowerMode, DisplayStatus, DisplayPixelFormat, SelfDiagnostic,
+    CtrlDisplay, DisplayBrightness, ContentAdaptiveBrightness, CabcMinimumBrightness,
+};
+use display::orientation::Rotation;
+use display::xpt2046::Calibration;
+use display::shapes::{Shape, Style, Line, RoundedRect};
+use display::font::{Font, Cp850, Scaled, Text};
+use display::layer::{Compositor, Blend, ConsoleLayer, ShapeLayer, TextLayer};
 
+const ROTATION: Rotation = Rotation::Deg0;
+const CROSSHAIR_COLOR: (u8, u8, u8) = (0, 255, 0);
+/// Distance of the touch label from the crosshair centre
+const LABEL_OFFSET: usize = 4;
+/// Margin and corner radius of the box behind the touch label
+const LABEL_PADDING: i32 = 4;
+const LABEL_BACKDROP_COLOR: (u8, u8, u8) = (0, 0, 0);
 
-#[entry]
+
+#[cfg_attr(not(test), entry)]
 fn main() -> ! {
     let mut cp = cortex_m::Peripherals::take().unwrap();
     let dp = stm32f429::Peripherals::take().unwrap();
@@ -57,11 +75,10 @@ fn main() -> ! {
     let mut gpiof = dp.GPIOF.split(&mut rcc.ahb1);
 
     let mut led_green = gpiob.pb0.into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
-    let mut led_blue = gpiob.pb7.into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
     let mut led_red = gpiob.pb14.into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
 
     let mut lcd_bl = gpiod.pd15.into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
-    let mut lcd_rst = gpiof.pf12.into_push_pull_output(&mut gpiof.moder, &mut gpiof.otyper);
+    let lcd_rst = gpiof.pf12.into_push_pull_output(&mut gpiof.moder, &mut gpiof.otyper);
     let lcd_dc = gpiof.pf13.into_push_pull_output(&mut gpiof.moder, &mut gpiof.otyper);
     let lcd_cs = gpiod.pd14.into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
     let ts_cs = gpiof.pf14.into_push_pull_output(&mut gpiof.moder, &mut gpiof.otyper);
@@ -75,30 +92,48 @@ fn main() -> ! {
 
     let dma_streams = dp.DMA2.split(&mut rcc.ahb1);
 
-    lcd_rst.set_low();
-    delay.delay_us(9u16);
-    lcd_rst.set_high();
-    delay.delay_us(300u16);
-
     lcd_bl.set_high();
     let mut display = Display::new(
         sck, miso, mosi,
         dp.SPI1, dma_streams.s3, rcc.apb2, clocks,
-        lcd_dc, lcd_cs,
+        lcd_rst, lcd_dc, lcd_cs,
         ts_pen, ts_busy, ts_cs,
         sd_cs,
+        DisplayConfig {
+            // Keep booting on panels from unknown batches
+            fallback: Some(Controller::Ili9486),
+            ..DisplayConfig::default()
+        },
         &mut delay
-    ).expect("display");
-    let mut cons = Console::new();
+    ).unwrap_or_else(|e| panic!("display: {}", e));
+    display.set_orientation(ROTATION, false).expect("orientation");
+    let width = display.width();
+    let height = display.height();
+    // Hardware scrolling moves lines of the native portrait orientation
+    let hw_scroll = ROTATION == Rotation::Deg0;
+    if hw_scroll {
+        display.set_scroll_region(0, 0).expect("scroll region");
+    }
+    let mut cons = Console::new(width, height);
+    if let Err(e) = display.detect_controller() {
+        writeln!(&mut cons, "{}, assuming {:?}", e, display.controller()).unwrap();
+    }
+    writeln!(&mut cons, "{:?}", display.read::<PowerMode>()).unwrap();
+    writeln!(&mut cons, "{:?}", display.read::<DisplayStatus>()).unwrap();
+    writeln!(&mut cons, "{:?}", display.read::<SelfDiagnostic>()).unwrap();
+    writeln!(&mut cons, "pixel format: {:?}", display.read::<DisplayPixelFormat>()
+             .map(|format| (format.rgb_format(), format.cpu_format()))).unwrap();
+    writeln!(&mut cons, "{:?} {:?}", display.read::<CtrlDisplay>(), display.read::<DisplayBrightness>()).unwrap();
+    writeln!(&mut cons, "{:?} {:?}", display.read::<ContentAdaptiveBrightness>(), display.read::<CabcMinimumBrightness>()).unwrap();
 
-    let mut t = 0;
     let mut touch = None;
     let mut prev_touch = touch.clone();
-    let x_min = 460;
-    let y_min = 800;
-    let x_max = 4000;
-    let y_max = 4000;
+    let calibration = Calibration::new(460, 800, 4000, 4000)
+        .expect("calibration");
     let mut z_repeat = 0;
+    let mut scroll_offset = 0;
+    let mut dirty = DirtyRegion::new();
+    dirty.invalidate(display.bounds());
     loop {
         led_red.set_high();
         let is_input = display.ts_input();
@@ -111,65 +146,113 @@ fn main() -> ! {
             }
             if z_repeat > 5 {
                 writeln!(&mut cons, "x: {} y: {}", x, y).unwrap();
-                let x = (x as usize).max(x_min).min(x_max);
-                let y = (y as usize).max(y_min).min(y_max);
-                touch = Some((
-                    WIDTH * (x - x_min) / (x_max - x_min),
-                    HEIGHT * ((y_max - y_min) - (y - y_min)) / (y_max - y_min),
-                    z
-                ));
+                let (x, y) = display.touch_position(&calibration, x, y);
+                touch = Some((x, y, z));
             } else {
                 touch = None;
             }
         } else {
             writeln!(&mut cons, "no input").unwrap();
         }
+        if touch != prev_touch {
+            writeln!(&mut cons, "touch: {:?}", touch).unwrap();
+        }
         led_red.set_low();
 
-        if touch != prev_touch || t < 2 {
+        let new_scroll_offset = if hw_scroll {
+            cons.scroll_offset()
+        } else {
+            0
+        };
+        if touch != prev_touch || new_scroll_offset != scroll_offset {
+            invalidate_crosshair(&mut dirty, prev_touch, scroll_offset, width, height);
+            invalidate_crosshair(&mut dirty, touch, new_scroll_offset, width, height);
             prev_touch = touch.clone();
-            writeln!(&mut cons, "touch: {:?}", touch).unwrap();
+        }
+        if new_scroll_offset != scroll_offset {
+            scroll_offset = new_scroll_offset;
+            display.scroll_to(scroll_offset as u16)
+                .expect("scroll_to");
+        }
+        cons.take_dirty(hw_scroll, |rect| dirty.invalidate(rect));
 
-            led_red.set_high();
-            // display.set_pixel_area(50, 100, 50, 100).unwrap();
-            let mut w = display.write_pixels()
-                .expect("write_pixels");
-            led_red.set_low();
-
-            for y in 0..HEIGHT {
-                led_blue.set_high();
-                let scanline = ScanLine::new(|x| {
-                    let tint = 255u8.saturating_sub((y >> 1).min(255) as u8);
-                    let mut r = tint >> 2;
-                    let mut g = 0;
-                    let mut b = tint >> 1;
-                    match touch {
-                        Some((px, py, _))
-                            if (x == px) || (y == py) => {
-                                r = 0;
-                                g = 255;
-                                b = 0;
-                            }
-                        _ => {}
-                    }
-
-                    if cons.get_pixel(x, y) {
-                        r = 255;
-                        g = 255;
-                        b = 255;
-                    }
-
-                    (r, g, b)
-                });
-                led_blue.set_low();
-
-                led_green.set_high();
-                w.write(scanline)
-                    .expect("write");
-                led_green.set_low();
+        if !dirty.is_empty() {
+            led_green.set_high();
+            let mut background = |_x: usize, y: usize| {
+                // Background scrolls along with the console
+                let tint = 255u8.saturating_sub((y >> 1).min(255) as u8);
+                (tint >> 2, 0, tint >> 1)
+            };
+            let mut crosshair_layers = crosshair(touch, scroll_offset, width, height)
+                .map(|lines| [
+                    ShapeLayer::new(lines[0], CROSSHAIR_COLOR),
+                    ShapeLayer::new(lines[1], CROSSHAIR_COLOR),
+                ]);
+            let mut label_layers = touch_label(touch, scroll_offset, height)
+                .map(|label| (
+                    ShapeLayer::new(label_backdrop(&label), LABEL_BACKDROP_COLOR),
+                    TextLayer::new(label, CROSSHAIR_COLOR),
+                ));
+            let mut text = ConsoleLayer {
+                console: &cons,
+                hardware_scroll: hw_scroll,
+                color: (255, 255, 255),
+            };
+
+            let mut layers = Compositor::new();
+            layers.push(&mut background, Blend::Opaque).unwrap();
+            if let Some(ref mut crosshair_layers) = crosshair_layers {
+                for layer in crosshair_layers.iter_mut() {
+                    layers.push(layer, Blend::Alpha(255)).unwrap();
+                }
+            }
+            if let Some((ref mut backdrop, ref mut label)) = label_layers {
+                layers.push(backdrop, Blend::Alpha(160)).unwrap();
+                layers.push(label, Blend::Alpha(255)).unwrap();
             }
+            layers.push(&mut text, Blend::Alpha(255)).unwrap();
+            dirty.render(&mut display, |x, y| layers.get_pixel(x, y))
+                .expect("render");
+            led_green.set_low();
         }
+    }
+}
+
+/// Vertical and horizontal line through `touch`, in frame memory
+/// coordinates
+fn crosshair(touch: Option<(usize, usize, u16)>, scroll_offset: usize, width: usize, height: usize) -> Option<[Line; 2]> {
+    touch.map(|(x, y, _)| {
+        // Screen row `y` shows frame memory row `frame_y`
+        let (x, frame_y) = (x as i32, ((y + scroll_offset) % height) as i32);
+        [Line::new(x, 0, x, height as i32 - 1),
+         Line::new(0, frame_y, width as i32 - 1, frame_y)]
+    })
+}
+
+/// Double-size label next to the centre of the crosshair at
+/// `touch`, in frame memory coordinates
+fn touch_label(touch: Option<(usize, usize, u16)>, scroll_offset: usize, height: usize) -> Option<Text<'static, Scaled<Cp850>>> {
+    touch.map(|(x, y, _)| {
+        let frame_y = (y + scroll_offset) % height;
+        Text::new(Scaled::new(Cp850::PROPORTIONAL, 2), "touch",
+                  x + LABEL_OFFSET, frame_y + LABEL_OFFSET)
+    })
+}
 
-        t += 1;
+/// Translucent rounded box behind `label`
+fn label_backdrop<F: Font>(label: &Text<F>) -> RoundedRect {
+    let bounds = label.bounds();
+    RoundedRect::new(bounds.x as i32 - LABEL_PADDING, bounds.y as i32 - LABEL_PADDING,
+                     bounds.width as i32 + 2 * LABEL_PADDING, bounds.height as i32 + 2 * LABEL_PADDING,
+                     LABEL_PADDING, Style::Fill)
+}
+
+/// Invalidate both lines and the label of the crosshair at `touch`
+fn invalidate_crosshair(dirty: &mut DirtyRegion, touch: Option<(usize, usize, u16)>, scroll_offset: usize, width: usize, height: usize) {
+    for line in crosshair(touch, scroll_offset, width, height).iter().flat_map(|lines| lines.iter()) {
+        dirty.invalidate(line.bounds());
+    }
+    if let Some(label) = touch_label(touch, scroll_offset, height) {
+        dirty.invalidate(label_backdrop(&label).bounds());
     }
 }
@@ -11,6 +11,10 @@ pub trait SpiDmaWrite {
     /// Asynchronous (DMA) write
     fn write_async(&mut self, buffer: Self::DmaBuffer) -> Result<(), Self::Error>;
 
+    /// Synchronous DMA write of `word` repeated `count` times,
+    /// without a memory buffer
+    fn write_repeated(&mut self, word: [u8; 2], count: usize) -> Result<(), Self::Error>;
+
     /// Wait for DMA completion
     fn flush(&mut self) -> Result<(), Self::Error>;
 }